@@ -2,53 +2,82 @@ fn main() {
     use std::env;
     use std::path::Path;
 
-    if cfg!(feature = "docs-rs") {
+    println!("cargo:rerun-if-env-changed=DOCS_RS");
+
+    if cfg!(feature = "docs-rs") || cfg!(feature = "mock") || env::var_os("DOCS_RS").is_some() {
         return;
     }
 
     #[cfg(feature = "static")]
     println!("cargo:rerun-if-changed=/usr/lib/pacman/lib/pkgconfig");
     println!("cargo:rerun-if-env-changed=ALPM_LIB_DIR");
+    println!("cargo:rerun-if-env-changed=ALPM_INCLUDE_DIR");
 
     if cfg!(feature = "static") && Path::new("/usr/lib/pacman/lib/pkgconfig").exists() {
         env::set_var("PKG_CONFIG_LIBDIR", "/usr/lib/pacman/lib/pkgconfig");
     }
 
-    if let Ok(dir) = env::var("ALPM_LIB_DIR") {
+    let lib_dir = env::var("ALPM_LIB_DIR").ok();
+    #[allow(unused_variables)]
+    let include_dir = env::var("ALPM_INCLUDE_DIR").ok();
+
+    if let Some(dir) = &lib_dir {
         println!("cargo:rustc-link-search={}", dir);
     }
 
-    #[allow(dead_code)]
     #[allow(unused_variables)]
-    let lib = pkg_config::Config::new()
+    let lib = match pkg_config::Config::new()
         .atleast_version("13.0.0")
         .statik(cfg!(feature = "static"))
         .probe("libalpm")
-        .unwrap();
+    {
+        Ok(lib) => Some(lib),
+        // pkg-config isn't needed if the user told us where libalpm is
+        Err(_) if lib_dir.is_some() => {
+            if cfg!(feature = "static") {
+                println!("cargo:rustc-link-lib=static=alpm");
+            } else {
+                println!("cargo:rustc-link-lib=alpm");
+            }
+            None
+        }
+        Err(err) => panic!(
+            "could not find libalpm using pkg-config: {}\n\n\
+             If libalpm is installed in a non standard location, set ALPM_LIB_DIR to the \
+             directory containing libalpm (and ALPM_INCLUDE_DIR to the directory containing \
+             alpm.h when using the generate feature).",
+            err
+        ),
+    };
 
     #[cfg(feature = "generate")]
     {
         let out_dir = env::var_os("OUT_DIR").unwrap();
         let dest_path = Path::new(&out_dir).join("ffi_generated.rs");
 
-        let header = lib
-            .include_paths
+        let include_paths = match (&include_dir, &lib) {
+            (Some(dir), _) => vec![Path::new(dir).to_path_buf()],
+            (None, Some(lib)) => lib.include_paths.clone(),
+            (None, None) => vec![Path::new("/usr/include").to_path_buf()],
+        };
+
+        let header = include_paths
             .iter()
             .map(|i| i.join("alpm.h"))
             .find(|i| i.exists())
-            .expect("could not find alpm.h");
-        let mut include = lib
-            .include_paths
+            .unwrap_or_else(|| {
+                panic!(
+                    "could not find alpm.h in {:?}, set ALPM_INCLUDE_DIR to the directory containing it",
+                    include_paths
+                )
+            });
+        println!("cargo:rerun-if-changed={}", header.display());
+
+        let include = include_paths
             .iter()
-            .map(|i| format!("-I{}", i.display().to_string()))
+            .map(|i| format!("-I{}", i.display()))
             .collect::<Vec<_>>();
 
-        println!("cargo:rerun-if-env-changed=ALPM_INCLUDE_DIR");
-        if let Ok(path) = env::var("ALPM_INCLUDE_DIR") {
-            include.clear();
-            include.insert(0, path);
-        }
-
         let bindings = bindgen::builder()
             .clang_args(&include)
             .header(header.display().to_string())
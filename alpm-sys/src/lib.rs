@@ -11,6 +11,9 @@ mod ffi_git;
 #[cfg(feature = "generate")]
 mod ffi_generated;
 
+#[cfg(feature = "mock")]
+mod mock;
+
 #[cfg(not(any(feature = "generate", feature = "git")))]
 pub use crate::ffi::*;
 
@@ -0,0 +1,325 @@
+//! A pure Rust implementation of the parts of libalpm that do not need a handle.
+//!
+//! This allows the ownership and freeing logic of the safe wrapper to be exercised under
+//! Miri and sanitizers, which can not call into the real libalpm. The implementations mirror
+//! the behaviour of lib/libalpm/alpm_list.c.
+//!
+//! The list API, dependencies and the conflict and missing dependency types that transactions
+//! hand back are mocked. Anything that needs a handle, database or package will fail to link
+//! (or abort under Miri) when called.
+
+use crate::alpm_depmod_t::*;
+use crate::{
+    alpm_conflict_t, alpm_depend_t, alpm_depmissing_t, alpm_fileconflict_t, alpm_list_fn_free,
+    alpm_list_t,
+};
+
+use std::ffi::{c_void, CStr};
+use std::mem::size_of;
+use std::os::raw::{c_char, c_ulong};
+use std::ptr;
+
+extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_list_free(list: *mut alpm_list_t) {
+    let mut it = list;
+    while !it.is_null() {
+        let next = (*it).next;
+        free(it as *mut c_void);
+        it = next;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_list_free_inner(list: *mut alpm_list_t, fn_: alpm_list_fn_free) {
+    let Some(fn_) = fn_ else {
+        return;
+    };
+    let mut it = list;
+    while !it.is_null() {
+        if !(*it).data.is_null() {
+            fn_((*it).data);
+        }
+        it = (*it).next;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_list_add(
+    mut list: *mut alpm_list_t,
+    data: *mut c_void,
+) -> *mut alpm_list_t {
+    alpm_list_append(&mut list, data);
+    list
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_list_append(
+    list: *mut *mut alpm_list_t,
+    data: *mut c_void,
+) -> *mut alpm_list_t {
+    let node = malloc(size_of::<alpm_list_t>()) as *mut alpm_list_t;
+    if node.is_null() {
+        return ptr::null_mut();
+    }
+    node.write(alpm_list_t {
+        data,
+        prev: ptr::null_mut(),
+        next: ptr::null_mut(),
+    });
+
+    if (*list).is_null() {
+        (*node).prev = node;
+        *list = node;
+    } else {
+        let last = alpm_list_last(*list);
+        (*last).next = node;
+        (*node).prev = last;
+        (**list).prev = node;
+    }
+
+    node
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_list_remove_item(
+    mut haystack: *mut alpm_list_t,
+    item: *mut alpm_list_t,
+) -> *mut alpm_list_t {
+    if haystack.is_null() || item.is_null() {
+        return haystack;
+    }
+
+    if item == haystack {
+        // removing the head node, which has a back reference to the tail
+        haystack = (*item).next;
+        if !haystack.is_null() {
+            (*haystack).prev = (*item).prev;
+        }
+        (*item).prev = ptr::null_mut();
+    } else if item == (*haystack).prev {
+        // removing the tail node, so the head's back reference needs fixing
+        if !(*item).prev.is_null() {
+            (*(*item).prev).next = (*item).next;
+            (*haystack).prev = (*item).prev;
+            (*item).prev = ptr::null_mut();
+        }
+    } else {
+        if !(*item).next.is_null() {
+            (*(*item).next).prev = (*item).prev;
+        }
+        if !(*item).prev.is_null() {
+            (*(*item).prev).next = (*item).next;
+        }
+    }
+
+    haystack
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_list_copy(list: *const alpm_list_t) -> *mut alpm_list_t {
+    let mut new = ptr::null_mut();
+    let mut it = list;
+    while !it.is_null() {
+        if alpm_list_append(&mut new, (*it).data).is_null() {
+            alpm_list_free(new);
+            return ptr::null_mut();
+        }
+        it = (*it).next;
+    }
+    new
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_list_nth(list: *const alpm_list_t, n: usize) -> *mut alpm_list_t {
+    let mut it = list as *mut alpm_list_t;
+    for _ in 0..n {
+        it = (*it).next;
+    }
+    it
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_list_next(list: *const alpm_list_t) -> *mut alpm_list_t {
+    if list.is_null() {
+        ptr::null_mut()
+    } else {
+        (*list).next
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_list_previous(list: *const alpm_list_t) -> *mut alpm_list_t {
+    if !list.is_null() && !(*(*list).prev).next.is_null() {
+        (*list).prev
+    } else {
+        ptr::null_mut()
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_list_last(list: *const alpm_list_t) -> *mut alpm_list_t {
+    if list.is_null() {
+        ptr::null_mut()
+    } else {
+        (*list).prev
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_list_count(list: *const alpm_list_t) -> usize {
+    let mut n = 0;
+    let mut it = list;
+    while !it.is_null() {
+        n += 1;
+        it = (*it).next;
+    }
+    n
+}
+
+unsafe fn strndup(s: *const c_char, n: usize) -> *mut c_char {
+    let new = malloc(n + 1) as *mut c_char;
+    if !new.is_null() {
+        ptr::copy_nonoverlapping(s, new, n);
+        *new.add(n) = 0;
+    }
+    new
+}
+
+// _alpm_hash_sdbm
+fn hash_sdbm(s: &[u8]) -> c_ulong {
+    s.iter().fold(0 as c_ulong, |hash, &c| {
+        (c as c_ulong)
+            .wrapping_add(hash << 6)
+            .wrapping_add(hash << 16)
+            .wrapping_sub(hash)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_dep_from_string(depstring: *const c_char) -> *mut alpm_depend_t {
+    if depstring.is_null() {
+        return ptr::null_mut();
+    }
+    let s = CStr::from_ptr(depstring).to_bytes();
+
+    let (dep, desc) = match s.windows(2).position(|w| w == b": ") {
+        Some(i) => (&s[..i], Some(&s[i + 2..])),
+        None => (s, None),
+    };
+
+    let find = |c| dep.iter().position(|&b| b == c);
+    let (name_len, mod_, version) = if let Some(i) = find(b'<') {
+        if dep.get(i + 1) == Some(&b'=') {
+            (i, ALPM_DEP_MOD_LE, Some(i + 2))
+        } else {
+            (i, ALPM_DEP_MOD_LT, Some(i + 1))
+        }
+    } else if let Some(i) = find(b'>') {
+        if dep.get(i + 1) == Some(&b'=') {
+            (i, ALPM_DEP_MOD_GE, Some(i + 2))
+        } else {
+            (i, ALPM_DEP_MOD_GT, Some(i + 1))
+        }
+    } else if let Some(i) = find(b'=') {
+        (i, ALPM_DEP_MOD_EQ, Some(i + 1))
+    } else {
+        (dep.len(), ALPM_DEP_MOD_ANY, None)
+    };
+
+    let depend = malloc(size_of::<alpm_depend_t>()) as *mut alpm_depend_t;
+    if depend.is_null() {
+        return ptr::null_mut();
+    }
+    let dup = |s: &[u8]| strndup(s.as_ptr() as *const c_char, s.len());
+    depend.write(alpm_depend_t {
+        name: dup(&dep[..name_len]),
+        version: version.map_or(ptr::null_mut(), |i| dup(&dep[i..])),
+        desc: desc.map_or(ptr::null_mut(), dup),
+        name_hash: hash_sdbm(&dep[..name_len]),
+        mod_,
+    });
+    depend
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_dep_compute_string(dep: *const alpm_depend_t) -> *mut c_char {
+    if dep.is_null() {
+        return ptr::null_mut();
+    }
+    let dep = &*dep;
+    let str = |s: *mut c_char| {
+        if s.is_null() {
+            &[][..]
+        } else {
+            CStr::from_ptr(s).to_bytes()
+        }
+    };
+
+    let opr: &[u8] = match dep.mod_ {
+        ALPM_DEP_MOD_ANY => b"",
+        ALPM_DEP_MOD_GE => b">=",
+        ALPM_DEP_MOD_LE => b"<=",
+        ALPM_DEP_MOD_EQ => b"=",
+        ALPM_DEP_MOD_LT => b"<",
+        ALPM_DEP_MOD_GT => b">",
+    };
+    let mut out = str(dep.name).to_vec();
+    if dep.mod_ != ALPM_DEP_MOD_ANY && !dep.version.is_null() {
+        out.extend_from_slice(opr);
+        out.extend_from_slice(str(dep.version));
+    }
+    if !dep.desc.is_null() {
+        out.extend_from_slice(b": ");
+        out.extend_from_slice(str(dep.desc));
+    }
+
+    strndup(out.as_ptr() as *const c_char, out.len())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_dep_free(dep: *mut alpm_depend_t) {
+    if dep.is_null() {
+        return;
+    }
+    free((*dep).name as *mut c_void);
+    free((*dep).version as *mut c_void);
+    free((*dep).desc as *mut c_void);
+    free(dep as *mut c_void);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_depmissing_free(miss: *mut alpm_depmissing_t) {
+    if miss.is_null() {
+        return;
+    }
+    alpm_dep_free((*miss).depend);
+    free((*miss).target as *mut c_void);
+    free((*miss).causingpkg as *mut c_void);
+    free(miss as *mut c_void);
+}
+
+// the packages are borrowed from the transaction, only the reason is owned
+#[no_mangle]
+pub unsafe extern "C" fn alpm_conflict_free(conflict: *mut alpm_conflict_t) {
+    if conflict.is_null() {
+        return;
+    }
+    alpm_dep_free((*conflict).reason);
+    free(conflict as *mut c_void);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn alpm_fileconflict_free(conflict: *mut alpm_fileconflict_t) {
+    if conflict.is_null() {
+        return;
+    }
+    free((*conflict).ctarget as *mut c_void);
+    free((*conflict).file as *mut c_void);
+    free((*conflict).target as *mut c_void);
+    free(conflict as *mut c_void);
+}
@@ -46,7 +46,7 @@ pub fn configure_alpm(alpm: &mut Alpm, conf: &Config) -> alpm::Result<()> {
     alpm.set_gpgdir(&*conf.gpg_dir)?;
     alpm.set_logfile(&*conf.log_file)?;
     alpm.set_ignorepkgs(conf.ignore_pkg.iter())?;
-    alpm.set_ignorepkgs(conf.ignore_pkg.iter())?;
+    alpm.set_ignoregroups(conf.ignore_group.iter())?;
     alpm.set_architectures(conf.architecture.iter())?;
     alpm.set_noupgrades(conf.no_upgrade.iter())?;
     alpm.set_noextracts(conf.no_extract.iter())?;
@@ -1,4 +1,6 @@
-use alpm::{Dep, DepModVer, Ver};
+use alpm::{AlpmList, Dep, DepModVer, Package, Pkg, Ver};
+
+use std::collections::HashSet;
 
 /// Checks if a dependency is satisfied by a package (name + version).
 pub fn satisfies_dep<S: AsRef<str>, V: AsRef<Ver>>(
@@ -80,10 +82,147 @@ fn satisfies_ver<V: AsRef<Ver>>(dep: impl AsRef<Dep>, version: V) -> bool {
     }
 }
 
+/// The kind of dependency a package has on another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DepKind {
+    /// A normal dependency.
+    Depend,
+    /// An optional dependency.
+    Optional,
+    /// A make dependency.
+    Make,
+    /// A check dependency.
+    Check,
+}
+
+/// A package found by [`RequiredBy`].
+#[derive(Debug, Clone, Copy)]
+pub struct RevDep<'a> {
+    /// The package that has the dependency.
+    pub pkg: &'a Package,
+    /// The package it depends on.
+    pub parent: &'a Pkg,
+    /// How many steps away from the original package this is, starting at 1.
+    pub depth: usize,
+    /// The kind of dependency on `parent`.
+    pub kind: DepKind,
+}
+
+/// Computes the packages that depend on a package, like `pactree -r`.
+///
+/// Unlike [`Pkg::required_by`] this can follow optional, make and check dependencies
+/// (make and check dependencies are only present in sync databases) and recurse to
+/// find the packages indirectly depending on the package.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequiredBy {
+    optional: bool,
+    make: bool,
+    check: bool,
+    depth: Option<usize>,
+}
+
+impl RequiredBy {
+    /// Create a new `RequiredBy` that only follows normal dependencies with no depth limit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also follow optional dependencies.
+    pub fn optional(mut self, optional: bool) -> Self {
+        self.optional = optional;
+        self
+    }
+
+    /// Also follow make dependencies.
+    pub fn make(mut self, make: bool) -> Self {
+        self.make = make;
+        self
+    }
+
+    /// Also follow check dependencies.
+    pub fn check(mut self, check: bool) -> Self {
+        self.check = check;
+        self
+    }
+
+    /// Stop after `depth` levels. A depth of 1 only finds packages directly depending on the
+    /// package.
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Find the packages in `pkgs` that depend on `pkg`.
+    ///
+    /// The packages are returned breadth first, each package appearing only once at the lowest
+    /// depth it was found at.
+    pub fn find<'a>(&self, pkg: &'a Pkg, pkgs: AlpmList<'a, &'a Package>) -> Vec<RevDep<'a>> {
+        let mut ret = Vec::new();
+        let mut seen = HashSet::new();
+        let mut current = vec![pkg];
+        let mut depth = 1;
+
+        seen.insert(pkg.name());
+
+        while !current.is_empty() && depth <= self.depth.unwrap_or(usize::MAX) {
+            let mut next = Vec::new();
+
+            for parent in current {
+                for candidate in pkgs {
+                    if seen.contains(candidate.name()) {
+                        continue;
+                    }
+
+                    if let Some(kind) = self.dep_kind(candidate, parent) {
+                        seen.insert(candidate.name());
+                        next.push(&**candidate);
+                        ret.push(RevDep {
+                            pkg: candidate,
+                            parent,
+                            depth,
+                            kind,
+                        });
+                    }
+                }
+            }
+
+            current = next;
+            depth += 1;
+        }
+
+        ret
+    }
+
+    fn dep_kind(&self, pkg: &Pkg, target: &Pkg) -> Option<DepKind> {
+        let satisfied = |deps: AlpmList<&Dep>| {
+            deps.iter().any(|dep| {
+                satisfies(
+                    dep,
+                    target.name(),
+                    target.version(),
+                    target.provides().iter(),
+                )
+            })
+        };
+
+        if satisfied(pkg.depends()) {
+            Some(DepKind::Depend)
+        } else if self.optional && satisfied(pkg.optdepends()) {
+            Some(DepKind::Optional)
+        } else if self.make && satisfied(pkg.makedepends()) {
+            Some(DepKind::Make)
+        } else if self.check && satisfied(pkg.checkdepends()) {
+            Some(DepKind::Check)
+        } else {
+            None
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alpm::{Depend, Version};
+    use alpm::{Alpm, Depend, Version};
 
     #[test]
     fn test_satisfies_ver() {
@@ -117,4 +256,29 @@ mod tests {
         assert!(satisfies_ver(Depend::new("foo"), Version::new("1")));
         assert!(satisfies_ver(Depend::new("foo"), Version::new("1.0.0")));
     }
+
+    #[test]
+    fn test_required_by() {
+        let handle = Alpm::new("/", "../alpm/tests/db").unwrap();
+        let db = handle.localdb();
+        let pkg = db.pkg("glibc").unwrap();
+
+        let direct = RequiredBy::new().depth(1).find(pkg, db.pkgs());
+        let mut names = direct.iter().map(|d| d.pkg.name()).collect::<Vec<_>>();
+        let required_by = pkg.required_by();
+        let mut expected = required_by.iter().collect::<Vec<_>>();
+        names.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(names, expected);
+        assert!(direct
+            .iter()
+            .all(|d| d.depth == 1 && d.kind == DepKind::Depend && d.parent.name() == "glibc"));
+
+        let all = RequiredBy::new().find(pkg, db.pkgs());
+        assert!(all.len() > direct.len());
+        assert!(all.windows(2).all(|w| w[0].depth <= w[1].depth));
+
+        let optional = RequiredBy::new().optional(true).find(pkg, db.pkgs());
+        assert!(optional.len() >= all.len());
+    }
 }
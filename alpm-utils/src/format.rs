@@ -0,0 +1,228 @@
+use alpm::{Db, Pkg};
+
+/// Hooks for colouring the output of [`PkgFormat`].
+///
+/// Each method is given the plain text of a part of the output and returns the text to
+/// display. The default implementations return the text unchanged.
+pub trait Colors {
+    /// Colours a repository name.
+    fn repo(&self, repo: &str) -> String {
+        repo.to_string()
+    }
+
+    /// Colours a package name.
+    fn name(&self, name: &str) -> String {
+        name.to_string()
+    }
+
+    /// Colours a package version.
+    fn version(&self, version: &str) -> String {
+        version.to_string()
+    }
+
+    /// Colours the list of groups, including the parentheses.
+    fn groups(&self, groups: &str) -> String {
+        groups.to_string()
+    }
+
+    /// Colours the installed marker, including the brackets.
+    fn installed(&self, installed: &str) -> String {
+        installed.to_string()
+    }
+}
+
+/// [`Colors`] that leave the output uncoloured.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoColors;
+
+impl Colors for NoColors {}
+
+/// Formats packages the way pacman does.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PkgFormat<'a, C = NoColors> {
+    colors: C,
+    localdb: Option<&'a Db>,
+}
+
+impl<'a> PkgFormat<'a, NoColors> {
+    /// Create a new uncoloured formatter.
+    pub fn new() -> Self {
+        PkgFormat::default()
+    }
+}
+
+impl<'a, C: Colors> PkgFormat<'a, C> {
+    /// Use `colors` to colour the output.
+    pub fn colors<C2: Colors>(self, colors: C2) -> PkgFormat<'a, C2> {
+        PkgFormat {
+            colors,
+            localdb: self.localdb,
+        }
+    }
+
+    /// Mark packages that are installed in `localdb`.
+    pub fn localdb(mut self, localdb: &'a Db) -> Self {
+        self.localdb = Some(localdb);
+        self
+    }
+
+    /// Formats a package as `repo/name version (group) [installed]`, as in the output of
+    /// `pacman -Ss`.
+    pub fn pkg(&self, pkg: &Pkg) -> String {
+        let mut out = String::new();
+
+        if let Some(db) = pkg.db() {
+            out.push_str(&self.colors.repo(db.name()));
+            out.push('/');
+        }
+        out.push_str(&self.colors.name(pkg.name()));
+        out.push(' ');
+        out.push_str(&self.colors.version(pkg.version()));
+
+        let groups = pkg.groups();
+        if !groups.is_empty() {
+            let groups = groups.iter().collect::<Vec<_>>().join(" ");
+            out.push(' ');
+            out.push_str(&self.colors.groups(&format!("({})", groups)));
+        }
+
+        if let Some(installed) = self.installed(pkg) {
+            out.push(' ');
+            out.push_str(&self.colors.installed(&installed));
+        }
+
+        out
+    }
+
+    /// Formats packages as a table with columns for the name, version and repository.
+    ///
+    /// Columns are aligned on the uncoloured text and each row ends with a newline.
+    pub fn table<'p, I: IntoIterator<Item = &'p Pkg>>(&self, pkgs: I) -> String {
+        let rows = pkgs
+            .into_iter()
+            .map(|pkg| {
+                let repo = pkg.db().map(|db| db.name()).unwrap_or("");
+                let mut row = vec![
+                    (pkg.name().to_string(), self.colors.name(pkg.name())),
+                    (
+                        pkg.version().to_string(),
+                        self.colors.version(pkg.version()),
+                    ),
+                    (repo.to_string(), self.colors.repo(repo)),
+                ];
+                if let Some(installed) = self.installed(pkg) {
+                    row.push((installed.clone(), self.colors.installed(&installed)));
+                }
+                row
+            })
+            .collect::<Vec<_>>();
+
+        layout(&rows)
+    }
+
+    fn installed(&self, pkg: &Pkg) -> Option<String> {
+        let local = self.localdb?.pkg(pkg.name()).ok()?;
+
+        if local.version() == pkg.version() {
+            Some("[installed]".to_string())
+        } else {
+            Some(format!("[installed: {}]", local.version()))
+        }
+    }
+}
+
+/// Aligns rows of text into columns separated by two spaces.
+///
+/// Rows may have different numbers of columns. Each row ends with a newline and trailing
+/// whitespace is not added.
+pub fn columns<S: AsRef<str>>(rows: &[Vec<S>]) -> String {
+    let rows = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|s| (s.as_ref().to_string(), s.as_ref().to_string()))
+                .collect()
+        })
+        .collect::<Vec<_>>();
+    layout(&rows)
+}
+
+// Each cell is (plain, display). Widths are measured on the plain text so colour codes in
+// the display text do not throw off the alignment.
+fn layout(rows: &[Vec<(String, String)>]) -> String {
+    let mut widths = Vec::new();
+    for row in rows {
+        for (i, (plain, _)) in row.iter().enumerate() {
+            let width = plain.chars().count();
+            match widths.get_mut(i) {
+                Some(w) if *w < width => *w = width,
+                Some(_) => (),
+                None => widths.push(width),
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for row in rows {
+        for (i, (plain, display)) in row.iter().enumerate() {
+            out.push_str(display);
+            if i + 1 < row.len() {
+                let pad = widths[i] - plain.chars().count() + 2;
+                out.push_str(&" ".repeat(pad));
+            }
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpm::{Alpm, SigLevel};
+
+    struct Brackets;
+
+    impl Colors for Brackets {
+        fn name(&self, name: &str) -> String {
+            format!("<{}>", name)
+        }
+    }
+
+    #[test]
+    fn test_columns() {
+        let rows = vec![vec!["a", "bb", "c"], vec!["dddd", "e"], vec![]];
+        assert_eq!(columns(&rows), "a     bb  c\ndddd  e\n\n");
+    }
+
+    #[test]
+    fn test_pkg_format() {
+        let handle = Alpm::new("/", "../alpm/tests/db").unwrap();
+        let db = handle.register_syncdb("core", SigLevel::NONE).unwrap();
+        let pkg = db.pkg("pacman").unwrap();
+        let local = handle.localdb().pkg("pacman").unwrap();
+
+        let format = PkgFormat::new();
+        assert_eq!(format.pkg(pkg), "core/pacman 5.1.3-1 (base base-devel)");
+
+        // the fixtures have the same version installed and in core
+        let format = format.localdb(handle.localdb());
+        assert_eq!(
+            format.pkg(pkg),
+            "core/pacman 5.1.3-1 (base base-devel) [installed]"
+        );
+
+        let format = format.colors(Brackets);
+        assert!(format.pkg(pkg).starts_with("core/<pacman> "));
+
+        let table = PkgFormat::new().colors(Brackets).table([&**pkg, &**local]);
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("<pacman>  "));
+        assert_eq!(
+            lines[0].find(&pkg.version().to_string()),
+            lines[1].find(&local.version().to_string())
+        );
+    }
+}
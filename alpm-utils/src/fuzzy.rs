@@ -0,0 +1,135 @@
+use alpm::{AlpmList, Db, Package};
+
+/// How a package name matched the query in [`fuzzy_search`]. Better matches compare lower.
+#[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash, Debug)]
+pub enum MatchKind {
+    /// The name is the query.
+    Exact,
+    /// The name starts with the query.
+    Prefix,
+    /// The name contains the query.
+    Substring,
+    /// The name contains every character of the query in order.
+    Subsequence,
+    /// The name is within the allowed edit distance of the query.
+    Distance,
+}
+
+/// A package found by [`fuzzy_search`].
+#[derive(Clone, Copy, Debug)]
+pub struct FuzzyMatch<'a> {
+    /// The package.
+    pub pkg: &'a Package,
+    /// How the name matched.
+    pub kind: MatchKind,
+    /// The edit distance between the name and the query.
+    pub distance: usize,
+}
+
+/// Searches package names case insensitively, returning the best matches first.
+///
+/// Unlike `Db::search` this does not use regex and also finds names that are close to the
+/// query, which gives a better ordering for interactive pickers. Names that do not contain
+/// the query are only included if they are within `max_distance` edits of it.
+pub fn fuzzy_search<'a>(
+    dbs: AlpmList<'_, &'a Db>,
+    query: &str,
+    max_distance: usize,
+) -> Vec<FuzzyMatch<'a>> {
+    let query = query.to_lowercase();
+    let mut ret = Vec::new();
+
+    for db in dbs {
+        for pkg in db.pkgs() {
+            let name = pkg.name().to_lowercase();
+            let distance = levenshtein(&name, &query);
+
+            let kind = if name == query {
+                MatchKind::Exact
+            } else if name.starts_with(&query) {
+                MatchKind::Prefix
+            } else if name.contains(&query) {
+                MatchKind::Substring
+            } else if is_subsequence(&query, &name) {
+                MatchKind::Subsequence
+            } else if distance <= max_distance {
+                MatchKind::Distance
+            } else {
+                continue;
+            };
+
+            ret.push(FuzzyMatch {
+                pkg,
+                kind,
+                distance,
+            });
+        }
+    }
+
+    ret.sort_by(|a, b| (a.kind, a.distance, a.pkg.name()).cmp(&(b.kind, b.distance, b.pkg.name())));
+    ret
+}
+
+/// The number of single character insertions, deletions and substitutions needed to turn
+/// `a` into `b`.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut cur = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let sub = prev[j] + (ca != cb) as usize;
+            cur[j + 1] = sub.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    prev[b.len()]
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpm::{Alpm, SigLevel};
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("abc", ""), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("pacman", "pacman"), 0);
+        assert_eq!(levenshtein("pacman", "packman"), 1);
+    }
+
+    #[test]
+    fn test_is_subsequence() {
+        assert!(is_subsequence("pcmn", "pacman"));
+        assert!(is_subsequence("", "pacman"));
+        assert!(!is_subsequence("nmcp", "pacman"));
+    }
+
+    #[test]
+    fn test_fuzzy_search() {
+        let handle = Alpm::new("/", "../alpm/tests/db").unwrap();
+        handle.register_syncdb("core", SigLevel::NONE).unwrap();
+
+        let pkgs = fuzzy_search(handle.syncdbs(), "LINUX", 0);
+        assert_eq!(pkgs[0].pkg.name(), "linux");
+        assert_eq!(pkgs[0].kind, MatchKind::Exact);
+        assert!(pkgs.iter().any(|m| m.kind == MatchKind::Prefix));
+        assert!(pkgs.windows(2).all(|w| w[0].kind <= w[1].kind));
+
+        let pkgs = fuzzy_search(handle.syncdbs(), "pacmna", 2);
+        let pacman = pkgs.iter().find(|m| m.pkg.name() == "pacman").unwrap();
+        assert_eq!(pacman.kind, MatchKind::Distance);
+        assert_eq!(pacman.distance, 2);
+    }
+}
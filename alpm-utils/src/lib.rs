@@ -11,10 +11,27 @@ mod db;
 /// Utils for dependency checking.
 #[cfg(feature = "alpm")]
 pub mod depends;
+/// Utils for formatting packages.
+#[cfg(feature = "alpm")]
+pub mod format;
+#[cfg(feature = "alpm")]
+mod fuzzy;
+#[cfg(feature = "alpm")]
+mod owned;
+#[cfg(feature = "alpm")]
+mod resolve;
 mod target;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 
 #[cfg(feature = "conf")]
 pub use crate::conf::*;
 #[cfg(feature = "alpm")]
 pub use crate::db::*;
+#[cfg(feature = "alpm")]
+pub use crate::fuzzy::*;
+#[cfg(feature = "alpm")]
+pub use crate::owned::*;
+#[cfg(feature = "alpm")]
+pub use crate::resolve::*;
 pub use crate::target::*;
@@ -0,0 +1,298 @@
+use alpm::{
+    Alpm, AlpmList, Db, Package, PackageReason, PackageValidation, Pkg, Result, VersionChange,
+};
+
+use std::fmt;
+
+use crate::{AsTarg, DbListExt};
+
+/// Owned information about a package.
+///
+/// Unlike [`Pkg`] this does not borrow from the handle, so it can be freely sent across
+/// threads or stored for later use.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct PkgInfo {
+    /// The database the package belongs to.
+    pub db: Option<String>,
+    /// The package name.
+    pub name: String,
+    /// The package version.
+    pub version: String,
+    /// The package base.
+    pub base: Option<String>,
+    /// The package description.
+    pub desc: Option<String>,
+    /// The upstream url.
+    pub url: Option<String>,
+    /// The package architecture.
+    pub arch: Option<String>,
+    /// The packager.
+    pub packager: Option<String>,
+    /// When the package was built.
+    pub build_date: i64,
+    /// When the package was installed, if it is installed.
+    pub install_date: Option<i64>,
+    /// The size of the package file.
+    pub size: i64,
+    /// The installed size of the package.
+    pub isize: i64,
+    /// Why the package was installed.
+    pub reason: PackageReason,
+    /// The package's licenses.
+    pub licenses: Vec<String>,
+    /// The groups the package belongs to.
+    pub groups: Vec<String>,
+    /// The package's dependencies.
+    pub depends: Vec<String>,
+    /// The package's optional dependencies.
+    pub optdepends: Vec<String>,
+    /// The package's make dependencies.
+    pub makedepends: Vec<String>,
+    /// The package's check dependencies.
+    pub checkdepends: Vec<String>,
+    /// What the package provides.
+    pub provides: Vec<String>,
+    /// What the package conflicts with.
+    pub conflicts: Vec<String>,
+    /// What the package replaces.
+    pub replaces: Vec<String>,
+}
+
+impl From<&Pkg> for PkgInfo {
+    fn from(pkg: &Pkg) -> Self {
+        PkgInfo {
+            db: pkg.db().map(|db| db.name().to_string()),
+            name: pkg.name().to_string(),
+            version: pkg.version().to_string(),
+            base: pkg.base().map(Into::into),
+            desc: pkg.desc().map(Into::into),
+            url: pkg.url().map(Into::into),
+            arch: pkg.arch().map(Into::into),
+            packager: pkg.packager().map(Into::into),
+            build_date: pkg.build_date(),
+            install_date: pkg.install_date(),
+            size: pkg.size(),
+            isize: pkg.isize(),
+            reason: pkg.reason(),
+            licenses: pkg.licenses().iter().map(Into::into).collect(),
+            groups: pkg.groups().iter().map(Into::into).collect(),
+            depends: pkg.depends().iter().map(|d| d.to_string()).collect(),
+            optdepends: pkg.optdepends().iter().map(|d| d.to_string()).collect(),
+            makedepends: pkg.makedepends().iter().map(|d| d.to_string()).collect(),
+            checkdepends: pkg.checkdepends().iter().map(|d| d.to_string()).collect(),
+            provides: pkg.provides().iter().map(|d| d.to_string()).collect(),
+            conflicts: pkg.conflicts().iter().map(|d| d.to_string()).collect(),
+            replaces: pkg.replaces().iter().map(|d| d.to_string()).collect(),
+        }
+    }
+}
+
+impl From<&Package> for PkgInfo {
+    fn from(pkg: &Package) -> Self {
+        PkgInfo::from(&**pkg)
+    }
+}
+
+/// A dependency of a package and whether it is installed.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct DepInfo {
+    /// The dependency.
+    pub dep: String,
+    /// Whether an installed package satisfies the dependency.
+    pub installed: bool,
+}
+
+/// Everything `pacman -Qi` or `pacman -Si` shows about a package, see [`details`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PkgDetails {
+    /// The package's metadata.
+    pub info: PkgInfo,
+    /// The package's dependencies.
+    pub depends: Vec<DepInfo>,
+    /// The package's optional dependencies. Like pacman, these count as installed if
+    /// anything installed provides their name, whatever the version.
+    pub optdepends: Vec<DepInfo>,
+    /// The packages that depend on the package. For installed packages these are other
+    /// installed packages, otherwise they come from the sync databases.
+    pub required_by: Vec<String>,
+    /// The packages that optionally depend on the package, from the same databases as
+    /// `required_by`.
+    pub optional_for: Vec<String>,
+    /// How the package was validated.
+    pub validation: PackageValidation,
+    /// The installed version of the package, if it is installed.
+    pub installed_version: Option<String>,
+    /// How much needs to be downloaded to install the package, 0 if it is already cached.
+    pub download_size: i64,
+}
+
+/// An available upgrade for an installed package.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Upgrade {
+    /// The database the new version comes from.
+    pub db: Option<String>,
+    /// The package name.
+    pub name: String,
+    /// The installed version.
+    pub old_version: String,
+    /// The version available in the sync databases.
+    pub new_version: String,
+}
+
+/// Formats the upgrade as `name old -> new`.
+impl fmt::Display for Upgrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let change = VersionChange::new(&self.old_version, &self.new_version);
+        write!(f, "{} {}", self.name, change)
+    }
+}
+
+/// Searches the databases for packages matching all terms.
+pub fn search<S: AsRef<str>>(
+    dbs: AlpmList<&Db>,
+    terms: impl IntoIterator<Item = S>,
+) -> Result<Vec<PkgInfo>> {
+    let terms = terms
+        .into_iter()
+        .map(|t| t.as_ref().to_string())
+        .collect::<Vec<_>>();
+    let mut ret = Vec::new();
+
+    for db in dbs {
+        let pkgs = db.search(terms.iter())?;
+        ret.extend(pkgs.iter().map(PkgInfo::from));
+    }
+
+    Ok(ret)
+}
+
+/// Looks up a single package in the databases.
+pub fn info<T: AsTarg>(dbs: AlpmList<&Db>, target: T) -> Result<PkgInfo> {
+    dbs.find_target(target).map(PkgInfo::from)
+}
+
+/// Gathers everything known about a package in one go.
+///
+/// Targets with a repo are looked up in that sync database. Other targets are looked up in the
+/// local database first, as `pacman -Qi` would, and then in the sync databases.
+pub fn details<T: AsTarg>(handle: &Alpm, target: T) -> Result<PkgDetails> {
+    let target = target.as_targ();
+    let localdb = handle.localdb();
+
+    let pkg = match localdb.pkg(target.pkg) {
+        Ok(pkg) if target.repo.is_none() => pkg,
+        _ => handle.syncdbs().find_target(target)?,
+    };
+
+    let local = localdb.pkgs();
+    let installed = |dep: &str| local.find_satisfier(dep).is_some();
+
+    Ok(PkgDetails {
+        info: PkgInfo::from(pkg),
+        depends: pkg
+            .depends()
+            .iter()
+            .map(|dep| {
+                let dep = dep.to_string();
+                DepInfo {
+                    installed: installed(&dep),
+                    dep,
+                }
+            })
+            .collect(),
+        optdepends: pkg
+            .optdepends()
+            .iter()
+            .map(|dep| DepInfo {
+                dep: dep.to_string(),
+                installed: installed(dep.name()),
+            })
+            .collect(),
+        required_by: pkg.required_by().iter().map(Into::into).collect(),
+        optional_for: pkg.optional_for().iter().map(Into::into).collect(),
+        validation: pkg.validation(),
+        installed_version: localdb
+            .pkg(pkg.name())
+            .ok()
+            .map(|p| p.version().to_string()),
+        download_size: pkg.download_size(),
+    })
+}
+
+/// Lists the installed packages that have a newer version in the sync databases.
+pub fn upgrades(handle: &Alpm) -> Vec<Upgrade> {
+    let syncdbs = handle.syncdbs();
+
+    handle
+        .localdb()
+        .pkgs()
+        .iter()
+        .filter_map(|pkg| {
+            let new = pkg.sync_new_version(syncdbs)?;
+            Some(Upgrade {
+                db: new.db().map(|db| db.name().to_string()),
+                name: pkg.name().to_string(),
+                old_version: pkg.version().to_string(),
+                new_version: new.version().to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpm::SigLevel;
+
+    fn assert_send<T: Send + 'static>(_: &T) {}
+
+    #[test]
+    fn test_owned() {
+        let handle = Alpm::new("/", "../alpm/tests/db").unwrap();
+        handle.register_syncdb("core", SigLevel::NONE).unwrap();
+        handle.register_syncdb("extra", SigLevel::NONE).unwrap();
+
+        let pkgs = search(handle.syncdbs(), ["linux"]).unwrap();
+        assert!(pkgs.iter().any(|p| p.name == "linux"));
+        assert_send(&pkgs);
+
+        let pkg = info(handle.syncdbs(), "core/linux").unwrap();
+        assert_eq!(pkg.name, "linux");
+        assert_eq!(pkg.db.as_deref(), Some("core"));
+
+        let upgrades = upgrades(&handle);
+        assert_send(&upgrades);
+        for upgrade in upgrades {
+            assert_ne!(upgrade.old_version, upgrade.new_version);
+            assert_eq!(
+                upgrade.to_string(),
+                format!(
+                    "{} {} -> {}",
+                    upgrade.name, upgrade.old_version, upgrade.new_version
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_details() {
+        let handle = Alpm::new("/", "../alpm/tests/db").unwrap();
+        handle.register_syncdb("core", SigLevel::NONE).unwrap();
+
+        let local = details(&handle, "pacman").unwrap();
+        assert_eq!(local.info.db.as_deref(), Some("local"));
+        assert_eq!(
+            local.installed_version.as_deref(),
+            Some(local.info.version.as_str())
+        );
+        assert_eq!(local.depends.len(), local.info.depends.len());
+        assert!(local.depends.iter().any(|d| d.installed));
+        assert_send(&local);
+
+        let sync = details(&handle, "core/pacman").unwrap();
+        assert_eq!(sync.info.db.as_deref(), Some("core"));
+        assert_eq!(sync.installed_version, local.installed_version);
+
+        assert!(details(&handle, "core/doesnotexist").is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use alpm::{Alpm, Error, LoadedPackage, Package, Result};
+
+use crate::{glob_match, DbListExt, Targ, TargetKind};
+
+/// What a target was resolved to by [`resolve_target`].
+#[derive(Debug)]
+pub enum Resolved<'a> {
+    /// Packages from the sync databases. Exact targets resolve to one package, globs to
+    /// every package they match.
+    Sync(Vec<&'a Package>),
+    /// A package file loaded from disk, or downloaded from a url first.
+    File(LoadedPackage<'a>),
+}
+
+/// Resolves a pacman style target.
+///
+/// Urls are downloaded with [`Alpm::fetch_pkgurl`] and loaded using the remote file
+/// siglevel, files are loaded using the local file siglevel and everything else is looked up
+/// in the sync databases.
+pub fn resolve_target<'a>(handle: &'a Alpm, target: &str) -> Result<Resolved<'a>> {
+    match TargetKind::parse(target) {
+        TargetKind::Url(url) => {
+            let files = handle.fetch_pkgurl([url].iter())?;
+            let file = files.first().ok_or(Error::PkgNotFound)?;
+            let pkg = handle.pkg_load(file, true, handle.remote_file_siglevel())?;
+            Ok(Resolved::File(pkg))
+        }
+        TargetKind::File(file) => {
+            let pkg = handle.pkg_load(file, true, handle.local_file_siglevel())?;
+            Ok(Resolved::File(pkg))
+        }
+        TargetKind::Glob(targ) => {
+            let pkgs = glob(handle, targ);
+            if pkgs.is_empty() {
+                Err(Error::PkgNotFound)
+            } else {
+                Ok(Resolved::Sync(pkgs))
+            }
+        }
+        TargetKind::Pkg(targ) => {
+            let pkg = handle
+                .syncdbs()
+                .find_target_satisfier(targ)
+                .ok_or(Error::PkgNotFound)?;
+            Ok(Resolved::Sync(vec![pkg]))
+        }
+    }
+}
+
+fn glob<'a>(handle: &'a Alpm, targ: Targ) -> Vec<&'a Package> {
+    handle
+        .syncdbs()
+        .into_iter()
+        .filter(|db| targ.repo.is_none() || targ.repo == Some(db.name()))
+        .flat_map(|db| db.pkgs())
+        .filter(|pkg| glob_match(targ.pkg, pkg.name()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpm::SigLevel;
+
+    #[test]
+    fn test_resolve_target() {
+        let handle = Alpm::new("/", "../alpm/tests/db").unwrap();
+        handle.register_syncdb("core", SigLevel::NONE).unwrap();
+        handle.register_syncdb("extra", SigLevel::NONE).unwrap();
+
+        match resolve_target(&handle, "core/linux").unwrap() {
+            Resolved::Sync(pkgs) => {
+                assert_eq!(pkgs.len(), 1);
+                assert_eq!(pkgs[0].name(), "linux");
+            }
+            Resolved::File(_) => panic!("expected sync package"),
+        }
+
+        match resolve_target(&handle, "core/linux*").unwrap() {
+            Resolved::Sync(pkgs) => {
+                assert!(pkgs.len() > 1);
+                assert!(pkgs.iter().all(|p| p.name().starts_with("linux")));
+                assert!(pkgs.iter().all(|p| p.db().unwrap().name() == "core"));
+            }
+            Resolved::File(_) => panic!("expected sync packages"),
+        }
+
+        match resolve_target(&handle, "../alpm/tests/pacman-5.1.3-1-x86_64.pkg.tar.xz").unwrap() {
+            Resolved::File(pkg) => assert_eq!(pkg.name(), "pacman"),
+            Resolved::Sync(_) => panic!("expected package file"),
+        }
+
+        assert_eq!(
+            resolve_target(&handle, "core/doesnotexist").unwrap_err(),
+            Error::PkgNotFound
+        );
+        assert_eq!(
+            resolve_target(&handle, "doesnotexist*").unwrap_err(),
+            Error::PkgNotFound
+        );
+    }
+}
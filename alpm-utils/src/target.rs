@@ -91,6 +91,77 @@ impl<'a> fmt::Display for Targ<'a> {
     }
 }
 
+/// A target as accepted by `pacman -S` and `pacman -U`.
+#[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash, Debug)]
+pub enum TargetKind<'a> {
+    /// A url to download a package file from.
+    Url(&'a str),
+    /// A path to a package file.
+    File(&'a str),
+    /// A package name containing `*` or `?` wildcards, optionally from a specific repository.
+    Glob(Targ<'a>),
+    /// A package to find, optionally from a specific repository and with a version constraint.
+    Pkg(Targ<'a>),
+}
+
+impl<'a> TargetKind<'a> {
+    /// Works out what kind of target `s` is.
+    ///
+    /// Anything containing `://` is a url. Paths starting with `/` or `.` and names
+    /// containing `.pkg.tar` are files. Everything else is treated as `repo/name`.
+    pub fn parse(s: &'a str) -> TargetKind<'a> {
+        if s.contains("://") {
+            TargetKind::Url(s)
+        } else if s.starts_with(['/', '.']) || s.contains(".pkg.tar") {
+            TargetKind::File(s)
+        } else {
+            let targ = Targ::from(s);
+            if targ.pkg.contains(['*', '?']) {
+                TargetKind::Glob(targ)
+            } else {
+                TargetKind::Pkg(targ)
+            }
+        }
+    }
+}
+
+/// Matches `s` against a pattern where `*` matches any number of characters and `?` matches
+/// exactly one.
+pub fn glob_match(pattern: &str, s: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let s = s.chars().collect::<Vec<_>>();
+    let (mut p, mut i) = (0, 0);
+    // where to resume from if the current attempt fails after a `*`
+    let mut backtrack = None;
+
+    while i < s.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, i));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                i += 1;
+            }
+            Some(&c) if c == s[i] => {
+                p += 1;
+                i += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bi)) => {
+                    p = bp + 1;
+                    i = bi + 1;
+                    backtrack = Some((bp, bi + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,4 +179,45 @@ mod tests {
         assert_eq!(target2.repo, None);
         assert_eq!(target2.pkg, "pkg2");
     }
+
+    #[test]
+    fn test_target_kind() {
+        assert_eq!(
+            TargetKind::parse("https://example.com/foo-1-1-any.pkg.tar.zst"),
+            TargetKind::Url("https://example.com/foo-1-1-any.pkg.tar.zst")
+        );
+        assert_eq!(
+            TargetKind::parse("foo-1-1-any.pkg.tar.zst"),
+            TargetKind::File("foo-1-1-any.pkg.tar.zst")
+        );
+        assert_eq!(TargetKind::parse("./foo"), TargetKind::File("./foo"));
+        assert_eq!(TargetKind::parse("/tmp/foo"), TargetKind::File("/tmp/foo"));
+        assert_eq!(
+            TargetKind::parse("core/foo=1.2.3"),
+            TargetKind::Pkg(Targ::new(Some("core"), "foo=1.2.3"))
+        );
+        assert_eq!(
+            TargetKind::parse("foo"),
+            TargetKind::Pkg(Targ::new(None, "foo"))
+        );
+        assert_eq!(
+            TargetKind::parse("extra/python-*"),
+            TargetKind::Glob(Targ::new(Some("extra"), "python-*"))
+        );
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "foo"));
+        assert!(glob_match("foo", "foo"));
+        assert!(!glob_match("foo", "food"));
+        assert!(glob_match("foo*", "food"));
+        assert!(glob_match("f?o", "foo"));
+        assert!(!glob_match("f?o", "fo"));
+        assert!(glob_match("python-*-git", "python-foo-bar-git"));
+        assert!(!glob_match("python-*-git", "python-foo-bar"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("*a*b*", "xxbxxaxx"));
+    }
 }
@@ -0,0 +1,548 @@
+//! Helpers for building alpm databases for tests.
+//!
+//! [`TestDb`] creates a temporary database directory that sync databases and installed
+//! packages can be written into, allowing tests to be written without vendoring binary
+//! database snapshots.
+//!
+//! ```no_run
+//! use alpm_utils::test_utils::{TestDb, TestPkg};
+//!
+//! let db = TestDb::new().unwrap();
+//! db.add_sync_db(
+//!     "core",
+//!     &[
+//!         TestPkg::new("foo", "1.0-1").depends(["bar>=2"]),
+//!         TestPkg::new("bar", "2.0-1").provides(["libbar.so=2-64"]),
+//!     ],
+//! )
+//! .unwrap();
+//! db.add_local_pkg(&TestPkg::new("bar", "1.0-1").files(["usr/", "usr/lib/", "usr/lib/libbar.so"]))
+//!     .unwrap();
+//!
+//! // let handle = Alpm::new("/", db.dbpath().to_str().unwrap()).unwrap();
+//! ```
+
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A package to be written to a test database.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TestPkg {
+    /// The package name.
+    pub name: String,
+    /// The package version, including the pkgrel.
+    pub version: String,
+    /// The package base.
+    pub base: Option<String>,
+    /// The package description.
+    pub desc: Option<String>,
+    /// The package architecture.
+    pub arch: String,
+    /// The build date.
+    pub build_date: i64,
+    /// The size of the package file.
+    pub size: i64,
+    /// The installed size of the package.
+    pub isize: i64,
+    /// The groups the package belongs to.
+    pub groups: Vec<String>,
+    /// The package's licenses.
+    pub licenses: Vec<String>,
+    /// The package's dependencies.
+    pub depends: Vec<String>,
+    /// The package's optional dependencies.
+    pub optdepends: Vec<String>,
+    /// The package's make dependencies.
+    pub makedepends: Vec<String>,
+    /// The package's check dependencies.
+    pub checkdepends: Vec<String>,
+    /// What the package provides.
+    pub provides: Vec<String>,
+    /// What the package conflicts with.
+    pub conflicts: Vec<String>,
+    /// What the package replaces.
+    pub replaces: Vec<String>,
+    /// The files owned by the package. Directories end with a `/`.
+    pub files: Vec<String>,
+    /// The files that are backed up on upgrade and removal.
+    pub backup: Vec<String>,
+    /// Whether the package was installed as a dependency.
+    pub depend: bool,
+    /// Extended data as name, value pairs. Only written to the local database.
+    pub xdata: Vec<(String, String)>,
+}
+
+fn strings<I, S>(iter: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    iter.into_iter().map(Into::into).collect()
+}
+
+impl TestPkg {
+    /// Create a new package with the given name and version.
+    pub fn new<S: Into<String>>(name: S, version: S) -> TestPkg {
+        TestPkg {
+            name: name.into(),
+            version: version.into(),
+            arch: "x86_64".into(),
+            ..Default::default()
+        }
+    }
+
+    /// Set the package base.
+    pub fn base<S: Into<String>>(mut self, base: S) -> TestPkg {
+        self.base = Some(base.into());
+        self
+    }
+
+    /// Set the package description.
+    pub fn desc<S: Into<String>>(mut self, desc: S) -> TestPkg {
+        self.desc = Some(desc.into());
+        self
+    }
+
+    /// Set the package architecture.
+    pub fn arch<S: Into<String>>(mut self, arch: S) -> TestPkg {
+        self.arch = arch.into();
+        self
+    }
+
+    /// Set the groups the package belongs to.
+    pub fn groups<I: IntoIterator<Item = S>, S: Into<String>>(mut self, groups: I) -> TestPkg {
+        self.groups = strings(groups);
+        self
+    }
+
+    /// Set the package's dependencies.
+    pub fn depends<I: IntoIterator<Item = S>, S: Into<String>>(mut self, deps: I) -> TestPkg {
+        self.depends = strings(deps);
+        self
+    }
+
+    /// Set the package's optional dependencies.
+    pub fn optdepends<I: IntoIterator<Item = S>, S: Into<String>>(mut self, deps: I) -> TestPkg {
+        self.optdepends = strings(deps);
+        self
+    }
+
+    /// Set the package's make dependencies.
+    pub fn makedepends<I: IntoIterator<Item = S>, S: Into<String>>(mut self, deps: I) -> TestPkg {
+        self.makedepends = strings(deps);
+        self
+    }
+
+    /// Set the package's check dependencies.
+    pub fn checkdepends<I: IntoIterator<Item = S>, S: Into<String>>(mut self, deps: I) -> TestPkg {
+        self.checkdepends = strings(deps);
+        self
+    }
+
+    /// Set what the package provides.
+    pub fn provides<I: IntoIterator<Item = S>, S: Into<String>>(mut self, deps: I) -> TestPkg {
+        self.provides = strings(deps);
+        self
+    }
+
+    /// Set what the package conflicts with.
+    pub fn conflicts<I: IntoIterator<Item = S>, S: Into<String>>(mut self, deps: I) -> TestPkg {
+        self.conflicts = strings(deps);
+        self
+    }
+
+    /// Set what the package replaces.
+    pub fn replaces<I: IntoIterator<Item = S>, S: Into<String>>(mut self, deps: I) -> TestPkg {
+        self.replaces = strings(deps);
+        self
+    }
+
+    /// Set the files owned by the package. Directories should end with a `/`.
+    pub fn files<I: IntoIterator<Item = S>, S: Into<String>>(mut self, files: I) -> TestPkg {
+        self.files = strings(files);
+        self
+    }
+
+    /// Set the files that are backed up on upgrade and removal.
+    pub fn backup<I: IntoIterator<Item = S>, S: Into<String>>(mut self, files: I) -> TestPkg {
+        self.backup = strings(files);
+        self
+    }
+
+    /// Mark the package as installed as a dependency.
+    pub fn as_depend(mut self) -> TestPkg {
+        self.depend = true;
+        self
+    }
+
+    /// Add an extended data field such as `pkgtype`.
+    pub fn xdata<S: Into<String>>(mut self, name: S, value: S) -> TestPkg {
+        self.xdata.push((name.into(), value.into()));
+        self
+    }
+
+    fn dir_name(&self) -> String {
+        format!("{}-{}", self.name, self.version)
+    }
+
+    fn write_common(&self, desc: &mut String) {
+        write_field(desc, "NAME", [&self.name]);
+        write_field(desc, "BASE", &self.base);
+        write_field(desc, "VERSION", [&self.version]);
+        write_field(desc, "DESC", &self.desc);
+        write_field(desc, "GROUPS", &self.groups);
+        write_field(desc, "LICENSE", &self.licenses);
+        write_field(desc, "ARCH", [&self.arch]);
+        write_field(desc, "BUILDDATE", [self.build_date.to_string()]);
+        write_field(desc, "REPLACES", &self.replaces);
+        write_field(desc, "CONFLICTS", &self.conflicts);
+        write_field(desc, "PROVIDES", &self.provides);
+        write_field(desc, "DEPENDS", &self.depends);
+        write_field(desc, "OPTDEPENDS", &self.optdepends);
+        write_field(desc, "MAKEDEPENDS", &self.makedepends);
+        write_field(desc, "CHECKDEPENDS", &self.checkdepends);
+    }
+
+    fn sync_desc(&self) -> String {
+        let mut desc = String::new();
+        let filename = format!("{}-{}.pkg.tar.zst", self.dir_name(), self.arch);
+        write_field(&mut desc, "FILENAME", [filename]);
+        write_field(&mut desc, "CSIZE", [self.size.to_string()]);
+        write_field(&mut desc, "ISIZE", [self.isize.to_string()]);
+        self.write_common(&mut desc);
+        desc
+    }
+
+    fn local_desc(&self) -> String {
+        let mut desc = String::new();
+        self.write_common(&mut desc);
+        write_field(&mut desc, "INSTALLDATE", [self.build_date.to_string()]);
+        write_field(&mut desc, "SIZE", [self.isize.to_string()]);
+        write_field(&mut desc, "REASON", [(self.depend as u8).to_string()]);
+        write_field(&mut desc, "VALIDATION", ["none"]);
+        let xdata = self.xdata.iter().map(|(k, v)| format!("{}={}", k, v));
+        write_field(&mut desc, "XDATA", xdata);
+        desc
+    }
+
+    fn local_files(&self) -> String {
+        let mut files = String::new();
+        write_field(&mut files, "FILES", &self.files);
+        let backup = self.backup.iter().map(|f| format!("{}\t{}", f, EMPTY_MD5));
+        write_field(&mut files, "BACKUP", backup);
+        files
+    }
+
+    // libarchive detects uncompressed mtree files by the #mtree header so there is no
+    // need to gzip this like makepkg does.
+    fn local_mtree(&self) -> String {
+        let mut mtree = String::from("#mtree\n");
+        for file in &self.files {
+            match file.strip_suffix('/') {
+                Some(dir) => {
+                    let _ = writeln!(mtree, "./{} time=0.0 mode=755 type=dir", dir);
+                }
+                None => {
+                    let _ = writeln!(
+                        mtree,
+                        "./{} time=0.0 mode=644 size=0 type=file md5digest={}",
+                        file, EMPTY_MD5
+                    );
+                }
+            }
+        }
+        mtree
+    }
+}
+
+// Files are created empty on disk so backup entries can all use the hash of an empty file.
+const EMPTY_MD5: &str = "d41d8cd98f00b204e9800998ecf8427e";
+
+fn write_field<I, S>(desc: &mut String, key: &str, values: I)
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut values = values.into_iter().peekable();
+    if values.peek().is_none() {
+        return;
+    }
+
+    let _ = writeln!(desc, "%{}%", key);
+    for value in values {
+        let _ = writeln!(desc, "{}", value.as_ref());
+    }
+    desc.push('\n');
+}
+
+/// A temporary database directory for tests.
+///
+/// Alongside the database a root directory is created that installed packages' files are
+/// written to. The directories are removed when this is dropped.
+#[derive(Debug)]
+pub struct TestDb {
+    dir: PathBuf,
+}
+
+impl TestDb {
+    /// Create a new empty database directory under the system's temporary directory.
+    pub fn new() -> io::Result<TestDb> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let n = COUNT.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("alpm-utils-test-{}-{}", process::id(), n));
+
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sync"))?;
+        fs::create_dir_all(dir.join("local"))?;
+        fs::create_dir_all(dir.join("root"))?;
+        fs::write(dir.join("local").join("ALPM_DB_VERSION"), "9\n")?;
+        Ok(TestDb { dir })
+    }
+
+    /// The path to pass as the dbpath to `Alpm::new`.
+    pub fn dbpath(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The path to pass as the root to `Alpm::new`.
+    pub fn root(&self) -> PathBuf {
+        self.dir.join("root")
+    }
+
+    /// Write a sync database containing `pkgs`.
+    ///
+    /// The database can then be registered with `Alpm::register_syncdb(name, ..)`.
+    pub fn add_sync_db(&self, name: &str, pkgs: &[TestPkg]) -> io::Result<()> {
+        let path = self.dir.join("sync").join(format!("{}.db", name));
+        let mut tar = TarWriter::new(File::create(path)?);
+
+        for pkg in pkgs {
+            let dir = pkg.dir_name();
+            tar.dir(&format!("{}/", dir))?;
+            tar.file(&format!("{}/desc", dir), pkg.sync_desc().as_bytes())?;
+        }
+
+        tar.finish()
+    }
+
+    /// Add `pkg` to the local database as if it was installed.
+    ///
+    /// The package's files are created, empty, under [`root`](TestDb::root) so that they can
+    /// later be removed or upgraded by a transaction.
+    pub fn add_local_pkg(&self, pkg: &TestPkg) -> io::Result<()> {
+        let dir = self.dir.join("local").join(pkg.dir_name());
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("desc"), pkg.local_desc())?;
+        fs::write(dir.join("files"), pkg.local_files())?;
+        fs::write(dir.join("mtree"), pkg.local_mtree())?;
+
+        let root = self.root();
+        for file in &pkg.files {
+            let path = root.join(file);
+            if file.ends_with('/') {
+                fs::create_dir_all(path)?;
+            } else {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                File::create(path)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+// Just enough of ustar for libarchive to read. Compression is not needed as libarchive
+// detects plain tar files.
+struct TarWriter<W> {
+    w: W,
+}
+
+impl<W: Write> TarWriter<W> {
+    fn new(w: W) -> Self {
+        TarWriter { w }
+    }
+
+    fn dir(&mut self, path: &str) -> io::Result<()> {
+        self.header(path, 0o755, 0, b'5')
+    }
+
+    fn file(&mut self, path: &str, data: &[u8]) -> io::Result<()> {
+        self.header(path, 0o644, data.len(), b'0')?;
+        self.w.write_all(data)?;
+        self.pad(data.len())
+    }
+
+    fn header(&mut self, path: &str, mode: u32, size: usize, kind: u8) -> io::Result<()> {
+        if path.len() > 100 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("path too long for tar header: {}", path),
+            ));
+        }
+
+        let mut header = [0u8; 512];
+        header[..path.len()].copy_from_slice(path.as_bytes());
+        octal(&mut header[100..108], mode as u64);
+        octal(&mut header[108..116], 0);
+        octal(&mut header[116..124], 0);
+        octal(&mut header[124..136], size as u64);
+        octal(&mut header[136..148], 0);
+        header[148..156].copy_from_slice(b"        ");
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+
+        let sum: u32 = header.iter().map(|&b| b as u32).sum();
+        octal(&mut header[148..155], sum as u64);
+
+        self.w.write_all(&header)
+    }
+
+    fn pad(&mut self, len: usize) -> io::Result<()> {
+        let rem = len % 512;
+        if rem != 0 {
+            self.w.write_all(&[0; 512][rem..])?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.w.write_all(&[0; 1024])?;
+        self.w.flush()
+    }
+}
+
+// writes a nul terminated, zero padded octal number
+fn octal(field: &mut [u8], n: u64) {
+    let s = format!("{:0width$o}\0", n, width = field.len() - 1);
+    field.copy_from_slice(s.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_desc() {
+        let pkg = TestPkg::new("foo", "1.0-1")
+            .desc("a package")
+            .depends(["bar>=2", "baz"]);
+        let desc = pkg.sync_desc();
+
+        assert!(desc.starts_with("%FILENAME%\nfoo-1.0-1-x86_64.pkg.tar.zst\n\n"));
+        assert!(desc.contains("%NAME%\nfoo\n\n"));
+        assert!(desc.contains("%DESC%\na package\n\n"));
+        assert!(desc.contains("%DEPENDS%\nbar>=2\nbaz\n\n"));
+        assert!(!desc.contains("%PROVIDES%"));
+    }
+
+    #[test]
+    fn test_tar() {
+        let mut data = Vec::new();
+        let mut tar = TarWriter::new(&mut data);
+        tar.dir("foo-1.0-1/").unwrap();
+        tar.file("foo-1.0-1/desc", b"hello").unwrap();
+        tar.finish().unwrap();
+
+        assert_eq!(data.len(), 512 * 5);
+        assert_eq!(&data[..10], b"foo-1.0-1/");
+        assert_eq!(&data[257..262], b"ustar");
+        assert_eq!(&data[1024..1029], b"hello");
+    }
+
+    #[test]
+    fn test_test_db() {
+        let db = TestDb::new().unwrap();
+        let dir = db.dbpath().to_path_buf();
+        db.add_sync_db("core", &[TestPkg::new("foo", "1.0-1")])
+            .unwrap();
+        assert!(dir.join("sync/core.db").exists());
+        drop(db);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_local_pkg() {
+        let db = TestDb::new().unwrap();
+        let pkg = TestPkg::new("foo", "1.0-1")
+            .files(["etc/", "etc/foo.conf", "usr/bin/foo"])
+            .backup(["etc/foo.conf"])
+            .as_depend();
+        db.add_local_pkg(&pkg).unwrap();
+
+        let dir = db.dbpath().join("local/foo-1.0-1");
+        let desc = fs::read_to_string(dir.join("desc")).unwrap();
+        assert!(desc.contains("%REASON%\n1\n\n"));
+        let files = fs::read_to_string(dir.join("files")).unwrap();
+        assert!(files.contains("%FILES%\netc/\netc/foo.conf\nusr/bin/foo\n\n"));
+        assert!(files.contains(&format!("%BACKUP%\netc/foo.conf\t{}\n", EMPTY_MD5)));
+        let mtree = fs::read_to_string(dir.join("mtree")).unwrap();
+        assert!(mtree.starts_with("#mtree\n./etc time=0.0 mode=755 type=dir\n"));
+
+        assert!(db.root().join("etc").is_dir());
+        assert!(db.root().join("usr/bin/foo").is_file());
+    }
+
+    #[cfg(feature = "alpm")]
+    #[test]
+    fn test_alpm() {
+        use alpm::{Alpm, SigLevel};
+
+        let db = TestDb::new().unwrap();
+        db.add_sync_db(
+            "core",
+            &[
+                TestPkg::new("foo", "1.0-1")
+                    .depends(["bar>=2"])
+                    .groups(["base"]),
+                TestPkg::new("bar", "2.0-1").provides(["libbar.so=2-64"]),
+            ],
+        )
+        .unwrap();
+
+        let handle = Alpm::new("/", db.dbpath().to_str().unwrap()).unwrap();
+        let core = handle.register_syncdb("core", SigLevel::NONE).unwrap();
+        let foo = core.pkg("foo").unwrap();
+        assert_eq!(foo.version().as_str(), "1.0-1");
+        assert_eq!(foo.depends().first().unwrap().to_string(), "bar>=2");
+        assert_eq!(core.group("base").unwrap().name(), "base");
+        assert!(core.pkgs().find_satisfier("libbar.so=2-64").is_some());
+    }
+
+    #[cfg(feature = "alpm")]
+    #[test]
+    fn test_alpm_local() {
+        use alpm::{Alpm, PackageReason};
+
+        let db = TestDb::new().unwrap();
+        db.add_local_pkg(
+            &TestPkg::new("foo", "1.0-1")
+                .files(["etc/", "etc/foo.conf"])
+                .backup(["etc/foo.conf"])
+                .xdata("pkgtype", "debug")
+                .as_depend(),
+        )
+        .unwrap();
+
+        let root = db.root();
+        let handle = Alpm::new(root.to_str().unwrap(), db.dbpath().to_str().unwrap()).unwrap();
+        let foo = handle.localdb().pkg("foo").unwrap();
+        assert_eq!(foo.reason(), PackageReason::Depend);
+        assert!(foo.files().contains("etc/foo.conf").is_some());
+        assert_eq!(foo.backup().first().unwrap().hash(), Some(EMPTY_MD5));
+        assert_eq!(foo.xdata().len(), 1);
+        assert_eq!(foo.pkgtype(), Some("debug"));
+    }
+}
@@ -0,0 +1,120 @@
+use alpm::{vercmp, Alpm, SigLevel};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn handle() -> Alpm {
+    let handle = Alpm::new("/", "tests/db").unwrap();
+    handle.register_syncdb("core", SigLevel::NONE).unwrap();
+    handle.register_syncdb("extra", SigLevel::NONE).unwrap();
+    handle.register_syncdb("community", SigLevel::NONE).unwrap();
+    handle
+}
+
+fn benchmark_pkgcache(c: &mut Criterion) {
+    let handle = handle();
+
+    c.bench_function("pkgcache", |b| {
+        b.iter(|| {
+            for db in handle.syncdbs() {
+                for pkg in db.pkgs() {
+                    black_box(pkg.name());
+                }
+            }
+        });
+    });
+}
+
+fn benchmark_search(c: &mut Criterion) {
+    let handle = handle();
+
+    c.bench_function("search", |b| {
+        b.iter(|| {
+            for db in handle.syncdbs() {
+                black_box(db.search(["linux", "headers"].iter()).unwrap());
+            }
+        });
+    });
+}
+
+fn benchmark_find_satisfier(c: &mut Criterion) {
+    let handle = handle();
+
+    c.bench_function("find_satisfier", |b| {
+        b.iter(|| {
+            let dbs = handle.syncdbs();
+            black_box(dbs.find_satisfier("libc.so=6-64"));
+            black_box(dbs.find_satisfier("sh"));
+            black_box(dbs.find_satisfier("python>=3"));
+        });
+    });
+}
+
+fn benchmark_vercmp_sort(c: &mut Criterion) {
+    let handle = handle();
+    let versions = handle
+        .syncdbs()
+        .iter()
+        .flat_map(|db| db.pkgs())
+        .map(|pkg| pkg.version().to_string())
+        .collect::<Vec<_>>();
+
+    c.bench_function("vercmp_sort", |b| {
+        b.iter(|| {
+            let mut versions = versions.clone();
+            versions.sort_by(|a, b| vercmp(a.as_str(), b.as_str()));
+            black_box(versions);
+        });
+    });
+}
+
+fn benchmark_upgrades(c: &mut Criterion) {
+    let handle = handle();
+
+    c.bench_function("upgrades", |b| {
+        b.iter(|| {
+            let syncdbs = handle.syncdbs();
+            for pkg in handle.localdb().pkgs() {
+                black_box(pkg.sync_new_version(syncdbs));
+            }
+        });
+    });
+}
+
+fn benchmark_pkgs_info(c: &mut Criterion) {
+    let handle = handle();
+    let db = handle
+        .syncdbs()
+        .iter()
+        .find(|db| db.name() == "extra")
+        .unwrap();
+    let names = db
+        .pkgs()
+        .iter()
+        .step_by(4)
+        .map(|pkg| pkg.name().to_string())
+        .collect::<Vec<_>>();
+
+    c.bench_function("pkgs_info", |b| {
+        b.iter(|| black_box(db.pkgs_info(&names)));
+    });
+
+    c.bench_function("pkgs_info_pkg", |b| {
+        b.iter(|| {
+            let pkgs = names
+                .iter()
+                .map(|n| db.pkg(n.as_str()).ok())
+                .collect::<Vec<_>>();
+            black_box(pkgs)
+        });
+    });
+}
+
+criterion_group!(
+    benches,
+    benchmark_pkgcache,
+    benchmark_search,
+    benchmark_find_satisfier,
+    benchmark_vercmp_sort,
+    benchmark_upgrades,
+    benchmark_pkgs_info
+);
+criterion_main!(benches);
@@ -1,7 +1,7 @@
 fn main() {
     #[cfg(feature = "checkver")]
     {
-        #[cfg(all(not(feature = "git"), not(feature = "docs-rs")))]
+        #[cfg(all(not(feature = "git"), not(feature = "docs-rs"), not(feature = "mock")))]
         {
             use alpm_sys::alpm_version;
             use std::ffi::CStr;
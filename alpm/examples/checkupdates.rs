@@ -0,0 +1,17 @@
+use alpm::{Alpm, SigLevel};
+
+// List the installed packages that have a newer version in the sync databases, like
+// checkupdates from pacman-contrib.
+fn main() {
+    let handle = Alpm::new("/", "tests/db").unwrap();
+
+    handle.register_syncdb("core", SigLevel::NONE).unwrap();
+    handle.register_syncdb("extra", SigLevel::NONE).unwrap();
+    handle.register_syncdb("community", SigLevel::NONE).unwrap();
+
+    for pkg in handle.localdb().pkgs() {
+        if let Some(new) = pkg.sync_new_version(handle.syncdbs()) {
+            println!("{} {} -> {}", pkg.name(), pkg.version(), new.version());
+        }
+    }
+}
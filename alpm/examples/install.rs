@@ -0,0 +1,58 @@
+use alpm::{Alpm, PrepareData, SigLevel, TransFlag};
+
+use std::env;
+use std::error::Error;
+use std::fs;
+
+// A minimal pacman -Sy that installs into a scratch root instead of the real system.
+fn main() -> Result<(), Box<dyn Error>> {
+    let targets = env::args().skip(1).collect::<Vec<_>>();
+
+    let root = env::temp_dir().join("alpm-install-example");
+    let dbpath = root.join("var/lib/pacman");
+    let cachedir = root.join("var/cache/pacman/pkg");
+    fs::create_dir_all(&dbpath)?;
+    fs::create_dir_all(&cachedir)?;
+
+    let mut handle = Alpm::new(root.to_str().unwrap(), dbpath.to_str().unwrap())?;
+    handle.add_cachedir(cachedir.to_str().unwrap())?;
+    // check signatures against the system keyring
+    handle.set_gpgdir("/etc/pacman.d/gnupg")?;
+
+    let core = handle.register_syncdb_mut("core", SigLevel::USE_DEFAULT)?;
+    core.add_server("https://geo.mirror.pkgbuild.com/core/os/x86_64")?;
+    handle.syncdbs_mut().update(false)?;
+
+    // released when dropped, so returning early does not leave the database locked
+    let mut trans = handle.trans(TransFlag::NONE)?;
+
+    for target in &targets {
+        let pkg = trans
+            .syncdbs()
+            .find_satisfier(target.as_str())
+            .ok_or_else(|| format!("target not found: {}", target))?;
+        trans.add_pkg(pkg).map_err(alpm::Error::from)?;
+    }
+
+    if let Err(err) = trans.prepare() {
+        if let Some(PrepareData::UnsatisfiedDeps(missing)) = err.data() {
+            for missing in missing.iter() {
+                eprintln!(
+                    ":: unable to satisfy dependency '{}' required by {}",
+                    missing.depend(),
+                    missing.target()
+                );
+            }
+        }
+        return Err(err.into());
+    }
+
+    println!("Packages ({}):", trans.trans_add().len());
+    for pkg in trans.trans_add() {
+        println!("  {}-{}", pkg.name(), pkg.version());
+    }
+
+    trans.commit()?;
+    trans.release()?;
+    Ok(())
+}
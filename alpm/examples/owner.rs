@@ -0,0 +1,23 @@
+use alpm::Alpm;
+
+use std::env;
+
+// Find which installed package owns a file, like pacman -Qo.
+fn main() {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "/usr/bin/pacman".to_string());
+    let handle = Alpm::new("/", "tests/db").unwrap();
+
+    // the file lists are relative to the root
+    let file = path.strip_prefix(handle.root()).unwrap_or(&path);
+
+    for pkg in handle.localdb().pkgs() {
+        if pkg.files().contains(file).is_some() {
+            println!("{} is owned by {} {}", path, pkg.name(), pkg.version());
+            return;
+        }
+    }
+
+    println!("no package owns {}", path);
+}
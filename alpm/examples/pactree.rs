@@ -0,0 +1,30 @@
+use alpm::{Alpm, Package};
+
+use std::collections::HashSet;
+use std::env;
+
+// Print the dependency tree of an installed package, like pactree from pacman-contrib.
+fn main() {
+    let name = env::args().nth(1).unwrap_or_else(|| "pacman".to_string());
+    let handle = Alpm::new("/", "tests/db").unwrap();
+    let pkg = handle.localdb().pkg(name.as_str()).unwrap();
+
+    let mut seen = HashSet::new();
+    print_tree(&handle, pkg, 0, &mut seen);
+}
+
+fn print_tree<'a>(handle: &'a Alpm, pkg: &'a Package, depth: usize, seen: &mut HashSet<&'a str>) {
+    println!("{:indent$}{}", "", pkg.name(), indent = depth * 2);
+
+    // only expand each package once
+    if !seen.insert(pkg.name()) {
+        return;
+    }
+
+    for dep in pkg.depends() {
+        match handle.localdb().pkgs().find_satisfier(dep.to_string()) {
+            Some(dep) => print_tree(handle, dep, depth + 1, seen),
+            None => println!("{:indent$}{} (missing)", "", dep, indent = (depth + 1) * 2),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::instrument::{track_free, Kind};
 use crate::{Alpm, Error, LoadedPackage, Package};
 
 use alpm_sys::*;
@@ -22,12 +23,17 @@ unsafe impl<'a> IntoPkgAdd for LoadedPackage<'a> {
         self.as_ptr()
     }
     unsafe fn added(self) {
+        // libalpm frees the package with the transaction
         std::mem::forget(self);
+        track_free(Kind::LoadedPackage);
     }
 }
 
 impl Alpm {
     pub fn trans_add_pkg<P: IntoPkgAdd>(&self, pkg: P) -> Result<(), AddError<P>> {
+        if let Err(error) = self.check_not_in_callback() {
+            return Err(AddError { error, pkg });
+        }
         let ret = unsafe { alpm_add_pkg(self.as_ptr(), pkg.as_alpm_pkg_t()) };
         let ok = self.check_ret(ret);
         match ok {
@@ -59,3 +65,37 @@ impl<P> From<AddError<P>> for Error {
         err.error
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SigLevel, TransFlag};
+
+    #[test]
+    fn test_trans_add_remove_pkg() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+        handle.register_syncdb("core", SigLevel::NONE).unwrap();
+        let pkg = handle.syncdbs().first().unwrap().pkg("pacman").unwrap();
+        let local = handle.localdb().pkg("linux").unwrap();
+
+        assert_eq!(
+            handle.trans_add_pkg(pkg).unwrap_err().error,
+            Error::TransNull
+        );
+
+        handle.trans_init(TransFlag::NO_LOCK).unwrap();
+        handle.trans_add_pkg(pkg).unwrap();
+        handle.trans_remove_pkg(local).unwrap();
+        assert_eq!(handle.trans_add().first().unwrap().name(), "pacman");
+        assert_eq!(handle.trans_remove().first().unwrap().name(), "linux");
+        handle.trans_release().unwrap();
+
+        let pkg = handle.syncdbs().first().unwrap().pkg("pacman").unwrap();
+        let local = handle.localdb().pkg("linux").unwrap();
+        assert_eq!(
+            handle.trans_add_pkg(pkg).unwrap_err().error,
+            Error::TransNull
+        );
+        assert_eq!(handle.trans_remove_pkg(local), Err(Error::TransNull));
+    }
+}
@@ -1,18 +1,47 @@
+use crate::db_stats::remove_db_stats;
+use crate::dload::remove_fetch_timeout;
+use crate::instrument::{track_free, track_new, Kind};
+use crate::interrupt::remove_interrupters;
+use crate::metrics::remove_metrics;
 use crate::utils::*;
-use crate::{Callbacks, Error, Result};
+use crate::{Callbacks, Error, Result, SigLevel};
 
-use std::ffi::{c_void, CString};
+use std::ffi::{c_char, c_void, CString};
 use std::fmt;
+use std::mem::ManuallyDrop;
 use std::os::raw::c_int;
-use std::ptr::NonNull;
+use std::ptr::{self, NonNull};
 
 use alpm_sys::*;
 use bitflags::bitflags;
 
 extern "C" {
     pub(crate) fn free(ptr: *mut c_void);
+    pub(crate) fn fnmatch(pattern: *const c_char, string: *const c_char, flags: c_int) -> c_int;
 }
 
+/// A libalpm handle.
+///
+/// Databases, packages and everything else returned by the handle borrow from it, so they can
+/// not outlive the handle:
+///
+/// ```compile_fail,E0505
+/// use alpm::Alpm;
+///
+/// let handle = Alpm::new("/", "tests/db").unwrap();
+/// let db = handle.localdb();
+/// drop(handle);
+/// println!("{}", db.name());
+/// ```
+///
+/// ```compile_fail,E0505
+/// use alpm::Alpm;
+///
+/// let handle = Alpm::new("/", "tests/db").unwrap();
+/// let pkg = handle.localdb().pkg("pacman").unwrap();
+/// handle.release().unwrap();
+/// println!("{}", pkg.name());
+/// ```
 #[allow(dead_code)]
 pub struct Alpm {
     handle: NonNull<alpm_handle_t>,
@@ -25,9 +54,16 @@ impl std::fmt::Debug for Alpm {
     }
 }
 
+// The callbacks are dropped after the handle is released, as libalpm may still call them
+// while releasing.
 impl Drop for Alpm {
     fn drop(&mut self) {
+        remove_interrupters(self.as_ptr());
         unsafe { alpm_release(self.as_ptr()) };
+        remove_metrics(self.as_ptr());
+        remove_db_stats(self.as_ptr());
+        remove_fetch_timeout(self.as_ptr());
+        track_free(Kind::Handle);
     }
 }
 
@@ -53,10 +89,13 @@ impl Alpm {
 
         match NonNull::new(handle) {
             None => unsafe { Err(Error::new(err)) },
-            Some(handle) => Ok(Alpm {
-                handle,
-                cbs: Callbacks::default(),
-            }),
+            Some(handle) => {
+                track_new(Kind::Handle);
+                Ok(Alpm {
+                    handle,
+                    cbs: Callbacks::default(),
+                })
+            }
         }
     }
 
@@ -64,17 +103,41 @@ impl Alpm {
         Alpm::new(root, db_path)
     }
 
+    /// Releases the handle, returning an error if libalpm failed to release it.
+    ///
+    /// Dropping the handle does the same but ignores the error.
     pub fn release(self) -> std::result::Result<(), ReleaseError> {
-        if unsafe { alpm_release(self.as_ptr()) } == 0 {
-            std::mem::forget(self);
+        let handle = ManuallyDrop::new(self);
+        remove_interrupters(handle.as_ptr());
+        let ret = unsafe { alpm_release(handle.as_ptr()) };
+        unsafe { drop(ptr::read(&handle.cbs)) };
+        remove_metrics(handle.as_ptr());
+        remove_db_stats(handle.as_ptr());
+        remove_fetch_timeout(handle.as_ptr());
+        track_free(Kind::Handle);
+
+        if ret == 0 {
             Ok(())
         } else {
-            std::mem::forget(self);
             Err(ReleaseError)
         }
     }
 
-    pub(crate) fn as_ptr(&self) -> *mut alpm_handle_t {
+    /// Takes ownership of a raw handle.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be a valid handle returned by `alpm_initialize` that is not owned
+    /// by anything else. It will be released when the returned [`Alpm`] is dropped.
+    pub unsafe fn from_ptr(handle: *mut alpm_handle_t) -> Alpm {
+        track_new(Kind::Handle);
+        Alpm {
+            handle: NonNull::new_unchecked(handle),
+            cbs: Callbacks::default(),
+        }
+    }
+
+    pub fn as_ptr(&self) -> *mut alpm_handle_t {
         self.handle.as_ptr()
     }
 
@@ -132,10 +195,48 @@ impl Capabilities {
     }
 }
 
+impl Alpm {
+    /// Whether libalpm was built with gpgme, without which signatures can not be checked and
+    /// siglevels other than [`SigLevel::NONE`] are rejected.
+    pub fn supports_signatures(&self) -> bool {
+        Capabilities::new().signatures()
+    }
+
+    /// Whether the handle can download files, either because libalpm was built with curl or
+    /// because a fetch callback is set.
+    pub fn supports_downloads(&self) -> bool {
+        supports_downloads(self.as_ptr())
+    }
+}
+
+pub(crate) fn supports_downloads(handle: *mut alpm_handle_t) -> bool {
+    Capabilities::new().downloader() || unsafe { alpm_option_get_fetchcb(handle).is_some() }
+}
+
+// Siglevels that do not need gpgme, anything else fails with WrongArgs or
+// MissingCapabilitySignatures when libalpm is built without it.
+pub(crate) fn check_siglevel(siglevel: SigLevel) -> Result<()> {
+    if siglevel == SigLevel::NONE
+        || siglevel == SigLevel::USE_DEFAULT
+        || Capabilities::new().signatures()
+    {
+        Ok(())
+    } else {
+        Err(Error::UnsupportedByLibalpm)
+    }
+}
+
+pub(crate) fn check_signatures() -> Result<()> {
+    if Capabilities::new().signatures() {
+        Ok(())
+    } else {
+        Err(Error::UnsupportedByLibalpm)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::SigLevel;
 
     #[test]
     fn test_lifetime() {
@@ -158,4 +259,35 @@ mod tests {
         drop(db);
         assert!(pkgs.len() > 10);
     }
+
+    #[test]
+    fn test_release() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        let data = std::rc::Rc::new(());
+        handle.set_log_cb(data.clone(), |_, _, _| ());
+        assert_eq!(std::rc::Rc::strong_count(&data), 2);
+
+        handle.release().unwrap();
+        assert_eq!(std::rc::Rc::strong_count(&data), 1);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+
+        let ret = handle.register_syncdb("core", SigLevel::PACKAGE);
+        if handle.supports_signatures() {
+            assert!(ret.is_ok());
+        } else {
+            assert_eq!(ret.unwrap_err(), Error::UnsupportedByLibalpm);
+        }
+
+        if !handle.supports_downloads() {
+            handle.register_syncdb("extra", SigLevel::NONE).unwrap();
+            let ret = handle.syncdbs_mut().update(false);
+            assert_eq!(ret.unwrap_err(), Error::UnsupportedByLibalpm);
+            handle.set_fetch_cb((), |_, _, _, _| crate::FetchResult::Err);
+            assert!(handle.supports_downloads());
+        }
+    }
 }
@@ -1,7 +1,9 @@
-use crate::{Package, PackageReason, Result};
+use crate::interrupt::with_trans_lock;
+use crate::{Alpm, Package, PackageReason, Result, TransFlag};
 
 use alpm_sys::*;
 
+use std::collections::{HashMap, HashSet};
 use std::mem::transmute;
 
 impl Package {
@@ -11,3 +13,212 @@ impl Package {
         self.check_ret(ret)
     }
 }
+
+impl Alpm {
+    /// Sets the install reason of many packages at once, like `pacman -D`.
+    ///
+    /// The database is locked once for all of the changes, so this fails if a transaction
+    /// is already running. Nothing is changed if any of the packages is not installed, and
+    /// if writing a change fails the changes already made are undone.
+    pub fn set_reasons<I, S>(&self, reasons: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (S, PackageReason)>,
+        S: AsRef<str>,
+    {
+        let localdb = self.localdb();
+        let mut changes = Vec::new();
+        for (name, reason) in reasons {
+            let pkg = localdb.pkg(name.as_ref())?;
+            changes.push((pkg, pkg.reason(), reason));
+        }
+
+        self.trans_init(TransFlag::NONE)?;
+
+        let mut ret = Ok(());
+        for (i, &(pkg, _, reason)) in changes.iter().enumerate() {
+            if let Err(err) = pkg.set_reason(reason) {
+                for &(pkg, old, _) in changes[..i].iter().rev() {
+                    let _ = pkg.set_reason(old);
+                }
+                ret = Err(err);
+                break;
+            }
+        }
+
+        let handle = self.as_ptr();
+        let release = with_trans_lock(handle, false, || unsafe { alpm_trans_release(handle) });
+        ret.and(self.check_ret(release))
+    }
+
+    /// Explicitly installed packages that no other explicitly installed package depends on.
+    ///
+    /// Installing these packages pulls in every other explicitly installed package as a
+    /// dependency, similar to Gentoo's world set. Optional dependencies are not taken into
+    /// account.
+    ///
+    /// When explicitly installed packages depend on each other in a cycle and nothing outside
+    /// the cycle depends on them, only the first of them in the local database is returned.
+    pub fn explicit_packages(&self) -> Vec<&Package> {
+        let explicit = self
+            .localdb()
+            .pkgs()
+            .iter()
+            .filter(|pkg| pkg.reason() == PackageReason::Explicit)
+            .collect::<Vec<_>>();
+        let index = explicit
+            .iter()
+            .enumerate()
+            .map(|(i, pkg)| (pkg.name(), i))
+            .collect::<HashMap<_, _>>();
+        let required_by = explicit
+            .iter()
+            .map(|pkg| {
+                pkg.required_by()
+                    .iter()
+                    .filter_map(|by| index.get(by).copied())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // every explicit package that transitively depends on each package
+        let ancestors = (0..explicit.len())
+            .map(|i| {
+                let mut seen = HashSet::new();
+                let mut stack = vec![i];
+                while let Some(j) = stack.pop() {
+                    for &k in &required_by[j] {
+                        if seen.insert(k) {
+                            stack.push(k);
+                        }
+                    }
+                }
+                seen
+            })
+            .collect::<Vec<_>>();
+
+        // a package is kept if all of its ancestors are in a cycle with it, and it comes
+        // first out of that cycle
+        explicit
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| {
+                ancestors[i]
+                    .iter()
+                    .all(|&j| j >= i && ancestors[j].contains(&i))
+            })
+            .map(|(_, pkg)| *pkg)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Alpm, Error, PackageReason};
+
+    use std::fs;
+    use std::path::Path;
+
+    fn copy_dir(from: &Path, to: &Path) {
+        fs::create_dir_all(to).unwrap();
+        for entry in fs::read_dir(from).unwrap() {
+            let entry = entry.unwrap();
+            fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_set_reasons() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let local = dir.join("local");
+        fs::create_dir_all(&local).unwrap();
+        fs::copy(
+            "tests/db/local/ALPM_DB_VERSION",
+            local.join("ALPM_DB_VERSION"),
+        )
+        .unwrap();
+        for pkg in ["pacman-5.1.3-1", "pacman-mirrorlist-20190109-1"] {
+            copy_dir(&Path::new("tests/db/local").join(pkg), &local.join(pkg));
+        }
+
+        let handle = Alpm::new("/", dir.to_str().unwrap()).unwrap();
+        let reason = |name| handle.localdb().pkg(name).unwrap().reason();
+        let old = reason("pacman");
+
+        let ret = handle.set_reasons([
+            ("pacman-mirrorlist", PackageReason::Explicit),
+            ("doesnotexist", PackageReason::Explicit),
+        ]);
+        assert_eq!(ret, Err(Error::PkgNotFound));
+        assert_eq!(reason("pacman"), old);
+
+        handle
+            .set_reasons([
+                ("pacman", PackageReason::Depend),
+                ("pacman-mirrorlist", PackageReason::Explicit),
+            ])
+            .unwrap();
+        assert_eq!(reason("pacman"), PackageReason::Depend);
+        assert_eq!(reason("pacman-mirrorlist"), PackageReason::Explicit);
+        assert!(!dir.join("db.lck").exists());
+
+        drop(handle);
+        let handle = Alpm::new("/", dir.to_str().unwrap()).unwrap();
+        let pkg = handle.localdb().pkg("pacman").unwrap();
+        assert_eq!(pkg.reason(), PackageReason::Depend);
+    }
+
+    #[test]
+    fn test_explicit_packages() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        let world = handle.explicit_packages();
+        assert!(!world.is_empty());
+
+        for pkg in &world {
+            assert_eq!(pkg.reason(), PackageReason::Explicit);
+            for by in pkg.required_by() {
+                let by = handle.localdb().pkg(by).unwrap();
+                assert_ne!(by.reason(), PackageReason::Explicit);
+            }
+        }
+
+        let explicit = handle
+            .localdb()
+            .pkgs()
+            .iter()
+            .filter(|pkg| pkg.reason() == PackageReason::Explicit)
+            .count();
+        assert!(world.len() <= explicit);
+    }
+
+    #[test]
+    fn test_explicit_packages_cycle() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let local = dir.join("local");
+        fs::create_dir_all(&local).unwrap();
+        fs::copy(
+            "tests/db/local/ALPM_DB_VERSION",
+            local.join("ALPM_DB_VERSION"),
+        )
+        .unwrap();
+        // a and b depend on each other, d depends on c
+        for (name, depends) in [("a", "b"), ("b", "a"), ("c", ""), ("d", "c")] {
+            let pkg = local.join(format!("{}-1-1", name));
+            fs::create_dir_all(&pkg).unwrap();
+            let desc = format!(
+                "%NAME%\n{}\n\n%VERSION%\n1-1\n\n%DEPENDS%\n{}\n\n",
+                name, depends
+            );
+            fs::write(pkg.join("desc"), desc).unwrap();
+        }
+
+        let handle = Alpm::new("/", dir.to_str().unwrap()).unwrap();
+        let world = handle
+            .explicit_packages()
+            .iter()
+            .map(|pkg| pkg.name())
+            .collect::<Vec<_>>();
+        assert_eq!(world, ["a", "d"]);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::instrument::{track_free, track_new, Kind};
 use crate::{Alpm, Pkg, Result, SigLevel};
 
 use alpm_sys::*;
@@ -16,6 +17,7 @@ impl<'a> Drop for LoadedPackage<'a> {
         unsafe {
             alpm_pkg_free(self.pkg.as_ptr());
         }
+        track_free(Kind::LoadedPackage);
     }
 }
 
@@ -65,6 +67,7 @@ impl Alpm {
             )
         };
         self.check_ret(ret)?;
+        track_new(Kind::LoadedPackage);
         Ok(LoadedPackage {
             pkg: unsafe { Pkg::from_ptr(pkg) },
         })
@@ -97,6 +100,9 @@ mod tests {
         assert_eq!(pkg.md5sum(), None);
         assert_eq!(pkg.sha256sum(), None);
         assert_eq!(pkg.base64_sig(), None);
+        let backup = pkg.backup().first().unwrap();
+        assert_eq!(backup.name(), "etc/pacman.conf");
+        assert_eq!(backup.hash(), None);
 
         Ok(())
     }
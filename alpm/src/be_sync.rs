@@ -1,17 +1,271 @@
 use crate::{Error, Result};
 use alpm_sys::*;
 
-use crate::{AlpmList, DbMut};
+use crate::alpm::supports_downloads;
+use crate::db_stats::{db_file_changes, record_update};
+use crate::dload::{finish_fetch_timeout, start_fetch_timeout};
+use crate::metrics::metrics;
+use crate::{Alpm, AlpmList, AlpmListMut, Db, DbMut};
+
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::time::Instant;
 
 impl<'a> AlpmList<'a, DbMut<'a>> {
+    /// Downloads the databases in the list, returning true if every database was already up
+    /// to date.
+    ///
+    /// Unless `force` is set, databases are only downloaded if they changed on the server.
+    /// Databases that are updated drop their package and group caches, which libalpm
+    /// rebuilds from the new files the next time they are queried, so groups read after the
+    /// update already reflect it.
     pub fn update(&self, force: bool) -> Result<bool> {
         let first = self.first().ok_or(Error::WrongArgs)?;
+        if !supports_downloads(first.handle_ptr()) {
+            return Err(Error::UnsupportedByLibalpm);
+        }
+        #[cfg(feature = "tracing")]
+        let _span = ::tracing::info_span!(
+            target: "alpm",
+            "db_update",
+            dbs = ?self.iter().map(|db| db.name().to_string()).collect::<Vec<_>>(),
+            force
+        )
+        .entered();
         let force = if force { 1 } else { 0 };
+        let before = db_file_changes(self);
+        let start = Instant::now();
+        start_fetch_timeout(first.handle_ptr());
         let ret = unsafe { alpm_db_update(first.handle_ptr(), self.as_ptr(), force) };
-        if ret == -1 {
+        let ret = if ret == -1 {
             Err(first.last_error())
         } else {
             Ok(ret == 1)
+        };
+        let ret = finish_fetch_timeout(first.handle_ptr(), ret);
+        record_update(self, &before, &ret);
+
+        if let Some(state) = metrics(first.handle_ptr()) {
+            let names = self
+                .iter()
+                .map(|db| db.name().to_string())
+                .collect::<Vec<_>>();
+            let names = names.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            state.metrics.db_update(&names, start.elapsed(), &ret);
+        }
+        ret
+    }
+}
+
+impl<'h> DbMut<'h> {
+    /// Downloads the database, returning true if it was already up to date.
+    ///
+    /// This is the same as calling [`AlpmList::update`] on a list of just this database.
+    pub fn update(&self, force: bool) -> Result<bool> {
+        let mut list = AlpmListMut::<&Db>::new();
+        list.push(self.inner);
+        let dbs = unsafe { AlpmList::<DbMut>::from_ptr(list.as_ptr()) };
+        dbs.update(force)
+    }
+}
+
+/// The result of repairing a sync database, see [`Alpm::repair_syncdbs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncDbRepair {
+    pub name: String,
+    /// Why the database could not be downloaded again, in which case its old files were put
+    /// back.
+    pub error: Option<Error>,
+}
+
+impl Alpm {
+    /// Finds sync databases that fail to load and downloads them again.
+    ///
+    /// Returns the databases that were broken and whether downloading each one worked.
+    /// Databases that load fine are left alone and nothing is downloaded if none are broken.
+    /// The files of a broken database are moved aside while it is downloaded and restored
+    /// if the download fails, so a failed repair leaves the database as it was.
+    pub fn repair_syncdbs(&mut self) -> Result<Vec<SyncDbRepair>> {
+        let broken = self
+            .syncdbs()
+            .iter()
+            .filter(|db| db.load_pkgs().is_err())
+            .map(|db| db.name().to_string())
+            .collect::<Vec<_>>();
+
+        if broken.is_empty() {
+            return Ok(Vec::new());
+        }
+        // checked before anything is moved, update checks again
+        if !supports_downloads(self.as_ptr()) {
+            return Err(Error::UnsupportedByLibalpm);
+        }
+
+        let mut ret = Vec::new();
+        for db in self.syncdbs_mut() {
+            if broken.iter().any(|name| name == db.name()) {
+                ret.push(SyncDbRepair {
+                    name: db.name().to_string(),
+                    error: repair_syncdb(&db).err(),
+                });
+            }
+        }
+        Ok(ret)
+    }
+}
+
+fn repair_syncdb(db: &DbMut) -> Result<()> {
+    let path = db.path().into_os_string();
+    let mut sig = path.clone();
+    sig.push(".sig");
+
+    let backup = |file: &OsStr| {
+        let mut backup = file.to_os_string();
+        backup.push(".bak");
+        backup
+    };
+    let restore = |moved: &[&OsString]| {
+        for file in moved {
+            let _ = fs::rename(backup(file), file);
+        }
+    };
+
+    let mut moved = Vec::new();
+    for file in [&path, &sig] {
+        match fs::rename(file, backup(file)) {
+            Ok(()) => moved.push(file),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(_) => {
+                restore(&moved);
+                return Err(Error::DbRemove);
+            }
+        }
+    }
+
+    if let Err(err) = db.update(true) {
+        restore(&moved);
+        return Err(err);
+    }
+    for file in moved {
+        let _ = fs::remove_file(backup(file));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Alpm, Error, FetchResult, SigLevel, SyncDbRepair};
+
+    use std::fs;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use tempfile::TempDir;
+
+    // A handle with an empty dbpath that downloads databases from tests/db/sync.
+    fn fetch_handle() -> (TempDir, Alpm) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("sync")).unwrap();
+        let handle = Alpm::new("/", dir.path().to_str().unwrap()).unwrap();
+        handle.set_fetch_cb((), |url, localpath, _, _| {
+            let file = url.rsplit('/').next().unwrap();
+            let from = Path::new("tests/db/sync").join(file);
+            match fs::copy(from, Path::new(localpath).join(file)) {
+                Ok(_) => FetchResult::Ok,
+                Err(_) => FetchResult::Err,
+            }
+        });
+        (dir, handle)
+    }
+
+    #[test]
+    fn test_repair_syncdbs() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+        handle.register_syncdb("core", SigLevel::NONE).unwrap();
+        handle.register_syncdb("extra", SigLevel::NONE).unwrap();
+
+        for db in handle.syncdbs() {
+            assert!(!db.load_pkgs().unwrap().is_empty());
         }
+        assert!(handle.repair_syncdbs().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_repair_broken_syncdb() {
+        let (dir, mut handle) = fetch_handle();
+        fs::write(dir.path().join("sync/core.db"), "not a database").unwrap();
+        let db = handle.register_syncdb_mut("core", SigLevel::NONE).unwrap();
+        db.add_server("file:///unused").unwrap();
+
+        let repaired = handle.repair_syncdbs().unwrap();
+        assert_eq!(
+            repaired,
+            [SyncDbRepair {
+                name: "core".to_string(),
+                error: None
+            }]
+        );
+        assert!(handle.db_stats("core").last_success.is_some());
+        assert!(!handle.syncdbs().first().unwrap().pkgs().is_empty());
+        assert!(!dir.path().join("sync/core.db.bak").exists());
+    }
+
+    #[test]
+    fn test_repair_restores_on_failure() {
+        let (dir, mut handle) = fetch_handle();
+        // there is no broken.db to download in tests/db/sync
+        fs::write(dir.path().join("sync/broken.db"), "not a database").unwrap();
+        let db = handle
+            .register_syncdb_mut("broken", SigLevel::NONE)
+            .unwrap();
+        db.add_server("file:///unused").unwrap();
+
+        let repaired = handle.repair_syncdbs().unwrap();
+        assert_eq!(repaired.len(), 1);
+        assert_eq!(repaired[0].name, "broken");
+        assert!(repaired[0].error.is_some());
+        let restored = fs::read_to_string(dir.path().join("sync/broken.db")).unwrap();
+        assert_eq!(restored, "not a database");
+        assert!(!dir.path().join("sync/broken.db.bak").exists());
+    }
+
+    #[test]
+    fn test_update() {
+        let (dir, mut handle) = fetch_handle();
+        for name in ["core", "extra"] {
+            let db = handle.register_syncdb_mut(name, SigLevel::NONE).unwrap();
+            db.add_server("file:///unused").unwrap();
+        }
+
+        // both databases were downloaded so they were not up to date
+        assert!(!handle.syncdbs_mut().update(false).unwrap());
+        assert!(dir.path().join("sync/core.db").exists());
+        assert!(handle.syncdbs().iter().all(|db| !db.pkgs().is_empty()));
+    }
+
+    #[test]
+    fn test_update_timeout() {
+        let (dir, mut handle) = fetch_handle();
+        let db = handle.register_syncdb_mut("core", SigLevel::NONE).unwrap();
+        db.add_server("file:///unused").unwrap();
+
+        handle.set_fetch_timeout(Some(Duration::ZERO));
+        assert_eq!(handle.syncdbs_mut().update(false), Err(Error::Timeout));
+        assert!(!dir.path().join("sync/core.db").exists());
+
+        handle.set_fetch_timeout(None);
+        assert!(!handle.syncdbs_mut().update(false).unwrap());
+    }
+
+    #[test]
+    fn test_db_update() {
+        let (_dir, mut handle) = fetch_handle();
+        let db = handle.register_syncdb_mut("core", SigLevel::NONE).unwrap();
+        assert_eq!(db.update(false).unwrap_err(), Error::ServerNone);
+
+        db.add_server("file:///unused").unwrap();
+        assert!(!db.update(true).unwrap());
+        assert!(!db.pkgs().is_empty());
     }
 }
@@ -1,4 +1,8 @@
-use crate::{free, Alpm, AnyDownloadEvent, AnyEvent, AnyQuestion, FetchResult, LogLevel, Progress};
+use crate::dload::fetch_with_timeout;
+use crate::{
+    free, Alpm, AnyDownloadEvent, AnyEvent, AnyQuestion, DownloadEvent, Error, FetchResult,
+    LogLevel, Progress, Result,
+};
 use alpm_sys::*;
 use std::cell::{RefCell, UnsafeCell};
 use std::ffi::{c_void, CStr};
@@ -27,36 +31,59 @@ pub(crate) struct Callbacks {
     pub(crate) fetch: Cb<dyn FetchCbTrait>,
 }
 
+impl Callbacks {
+    fn in_use(&self) -> bool {
+        unsafe {
+            (*self.log.get()).as_ref().is_some_and(|cb| cb.in_use())
+                || (*self.dl.get()).as_ref().is_some_and(|cb| cb.in_use())
+                || (*self.event.get()).as_ref().is_some_and(|cb| cb.in_use())
+                || (*self.progress.get())
+                    .as_ref()
+                    .is_some_and(|cb| cb.in_use())
+                || (*self.question.get())
+                    .as_ref()
+                    .is_some_and(|cb| cb.in_use())
+                || (*self.fetch.get()).as_ref().is_some_and(|cb| cb.in_use())
+        }
+    }
+}
+
 pub(crate) trait LogCbTrait {
     fn call(&self, level: LogLevel, s: &str);
     fn assert_unlocked(&self);
+    fn in_use(&self) -> bool;
 }
 
 pub(crate) trait DlCbTrait {
     fn call(&self, filename: &str, event: AnyDownloadEvent);
     fn assert_unlocked(&self);
+    fn in_use(&self) -> bool;
 }
 
 pub(crate) trait EventCbTrait {
     fn call(&self, event: AnyEvent);
     fn handle(&self) -> *mut alpm_handle_t;
     fn assert_unlocked(&self);
+    fn in_use(&self) -> bool;
 }
 
 pub(crate) trait ProgressCbTrait {
     fn call(&self, progress: Progress, pkgname: &str, percent: i32, howmany: usize, current: usize);
     fn assert_unlocked(&self);
+    fn in_use(&self) -> bool;
 }
 
 pub(crate) trait QuestionCbTrait {
     fn call(&self, question: AnyQuestion);
     fn handle(&self) -> *mut alpm_handle_t;
     fn assert_unlocked(&self);
+    fn in_use(&self) -> bool;
 }
 
 pub(crate) trait FetchCbTrait {
     fn call(&self, url: &str, filename: &str, force: bool) -> FetchResult;
     fn assert_unlocked(&self);
+    fn in_use(&self) -> bool;
 }
 
 struct LogCbImpl<T, F>(RefCell<(F, T)>);
@@ -70,12 +97,16 @@ impl<T, F: FnMut(LogLevel, &str, &mut T)> LogCbTrait for LogCbImpl<T, F> {
     fn assert_unlocked(&self) {
         self.0.try_borrow_mut().expect("callback is in use");
     }
+    fn in_use(&self) -> bool {
+        self.0.try_borrow_mut().is_err()
+    }
 }
 
-struct DlCbImpl<T, F>(RefCell<(F, T)>);
+struct DlCbImpl<T, F>(RefCell<(F, T)>, *mut alpm_handle_t);
 
 impl<T, F: FnMut(&str, AnyDownloadEvent, &mut T)> DlCbTrait for DlCbImpl<T, F> {
     fn call(&self, s: &str, event: AnyDownloadEvent) {
+        crate::metrics::download_event(self.1, s, &event);
         let mut cb = self.0.borrow_mut();
         let cb = &mut *cb;
         (cb.0)(s, event, &mut cb.1)
@@ -83,6 +114,9 @@ impl<T, F: FnMut(&str, AnyDownloadEvent, &mut T)> DlCbTrait for DlCbImpl<T, F> {
     fn assert_unlocked(&self) {
         self.0.try_borrow_mut().expect("callback is in use");
     }
+    fn in_use(&self) -> bool {
+        self.0.try_borrow_mut().is_err()
+    }
 }
 
 struct EventCbImpl<T, F>(RefCell<(F, T)>, *mut alpm_handle_t);
@@ -97,6 +131,9 @@ impl<T, F: FnMut(AnyEvent, &mut T)> EventCbTrait for EventCbImpl<T, F> {
     fn assert_unlocked(&self) {
         self.0.try_borrow_mut().expect("callback is in use");
     }
+    fn in_use(&self) -> bool {
+        self.0.try_borrow_mut().is_err()
+    }
 
     fn handle(&self) -> *mut alpm_handle_t {
         self.1
@@ -123,6 +160,9 @@ impl<T, F: FnMut(Progress, &str, i32, usize, usize, &mut T)> ProgressCbTrait
     fn assert_unlocked(&self) {
         self.0.try_borrow_mut().expect("callback is in use");
     }
+    fn in_use(&self) -> bool {
+        self.0.try_borrow_mut().is_err()
+    }
 }
 
 struct QuestionCbImpl<T, F>(RefCell<(F, T)>, *mut alpm_handle_t);
@@ -136,6 +176,9 @@ impl<T, F: FnMut(AnyQuestion, &mut T)> QuestionCbTrait for QuestionCbImpl<T, F>
     fn assert_unlocked(&self) {
         self.0.try_borrow_mut().expect("callback is in use");
     }
+    fn in_use(&self) -> bool {
+        self.0.try_borrow_mut().is_err()
+    }
 
     fn handle(&self) -> *mut alpm_handle_t {
         self.1
@@ -154,6 +197,9 @@ impl<T, F: FnMut(&str, &str, bool, &mut T) -> FetchResult> FetchCbTrait for Fetc
     fn assert_unlocked(&self) {
         self.0.try_borrow_mut().expect("callback is in use");
     }
+    fn in_use(&self) -> bool {
+        self.0.try_borrow_mut().is_err()
+    }
 }
 
 pub struct RawLogCb {
@@ -229,6 +275,14 @@ impl fmt::Debug for RawFetchCb {
 }
 
 impl Alpm {
+    pub(crate) fn check_not_in_callback(&self) -> Result<()> {
+        if self.cbs.in_use() {
+            Err(Error::InCallback)
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn set_log_cb<T: 'static, F: FnMut(LogLevel, &str, &mut T) + 'static>(
         &self,
         data: T,
@@ -258,13 +312,30 @@ impl Alpm {
         if let Some(cb) = c.as_ref() {
             cb.assert_unlocked()
         }
-        let ctx = DlCbImpl(RefCell::new((f, data)));
+        let ctx = DlCbImpl(RefCell::new((f, data)), self.as_ptr());
         let ctx = Box::new(ctx);
         let cb = dlcb::<DlCbImpl<T, F>>;
         unsafe { alpm_option_set_dlcb(self.as_ptr(), Some(cb), &*ctx as *const _ as *mut _) };
         c.replace(ctx);
     }
 
+    /// Sets a download callback that is only called with the progress of each file.
+    ///
+    /// The callback is given the file name and the bytes downloaded so far out of the total,
+    /// which is 0 if the server did not send a size. This replaces any callback set with
+    /// [`Alpm::set_dl_cb`].
+    pub fn set_dl_progress_cb<T: 'static, F: FnMut(&str, i64, i64, &mut T) + 'static>(
+        &self,
+        data: T,
+        mut f: F,
+    ) {
+        self.set_dl_cb(data, move |filename, event, data| {
+            if let DownloadEvent::Progress(progress) = event.event() {
+                f(filename, progress.downloaded, progress.total, data)
+            }
+        })
+    }
+
     pub fn set_event_cb<T: 'static, F: FnMut(AnyEvent, &mut T) + 'static>(&self, data: T, f: F) {
         let c = unsafe { &mut *self.cbs.event.get() };
         if let Some(cb) = c.as_ref() {
@@ -277,6 +348,10 @@ impl Alpm {
         c.replace(ctx);
     }
 
+    /// Sets a callback that is called as each step of a transaction progresses.
+    ///
+    /// The callback is given the step, the name of the package it is working on, the percent
+    /// done, the number of packages in the step and the position of this package in it.
     pub fn set_progress_cb<
         T: 'static,
         F: FnMut(Progress, &str, i32, usize, usize, &mut T) + 'static,
@@ -321,10 +396,19 @@ impl Alpm {
         if let Some(cb) = c.as_ref() {
             cb.assert_unlocked()
         }
+        let handle = self.as_ptr();
+        let mut f = f;
+        let f = move |url: &str, localpath: &str, force: bool, data: &mut T| {
+            fetch_with_timeout(handle, || f(url, localpath, force, data))
+        };
         let ctx = FetchCbImpl(RefCell::new((f, data)));
         let ctx = Box::new(ctx);
-        let cb = fetchcb::<FetchCbImpl<T, F>>;
-        unsafe { alpm_option_set_fetchcb(self.as_ptr(), Some(cb), &*ctx as *const _ as *mut _) };
+        // the wrapped closure can not be named
+        fn cb_for<C: FetchCbTrait>(_: &C) -> alpm_cb_fetch {
+            Some(fetchcb::<C>)
+        }
+        let cb = cb_for(&*ctx);
+        unsafe { alpm_option_set_fetchcb(self.as_ptr(), cb, &*ctx as *const _ as *mut _) };
         c.replace(ctx);
     }
 
@@ -543,11 +627,17 @@ extern "C" fn eventcb<C: EventCbTrait>(ctx: *mut c_void, event: *mut alpm_event_
 }
 
 extern "C" fn questioncb<C: QuestionCbTrait>(ctx: *mut c_void, question: *mut alpm_question_t) {
-    let _ = panic::catch_unwind(|| {
+    let ret = panic::catch_unwind(|| {
         let cb = unsafe { &*(ctx as *const C) };
         let question = unsafe { AnyQuestion::new(question) };
         cb.call(question);
     });
+
+    // The callback may have set an answer before panicking, so answer no instead, which is
+    // also the first provider when selecting a provider.
+    if ret.is_err() {
+        unsafe { (*question).any.answer = 0 };
+    }
 }
 
 extern "C" fn progresscb<C: ProgressCbTrait>(
@@ -572,7 +662,7 @@ mod tests {
     use super::*;
     use crate::{
         log_action, version, AnyDownloadEvent, AnyEvent, AnyQuestion, Capabilities, DownloadEvent,
-        Event, FetchResult, Progress, Question, SigLevel,
+        Event, FetchResult, Progress, Question, SigLevel, TransFlag,
     };
     use std::cell::Cell;
     use std::rc::Rc;
@@ -723,6 +813,24 @@ mod tests {
         handle.register_syncdb("core", SigLevel::NONE).unwrap();
     }
 
+    #[test]
+    fn test_cb_reentrant() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        let handle = Rc::new(handle);
+        let ret = Rc::new(Cell::new(None));
+
+        handle.set_log_cb(
+            (Rc::downgrade(&handle), Rc::clone(&ret)),
+            |_, _, (handle, ret)| {
+                let handle = handle.upgrade().unwrap();
+                ret.set(Some(handle.trans_init(TransFlag::NONE)));
+            },
+        );
+        handle.register_syncdb("core", SigLevel::NONE).unwrap();
+
+        assert_eq!(ret.get(), Some(Err(Error::InCallback)));
+    }
+
     #[ignore]
     #[test]
     fn test_cb_refcell_mut() {
@@ -756,4 +864,96 @@ mod tests {
         Rc::get_mut(&mut val).unwrap();
         drop(handle);
     }
+
+    #[test]
+    fn test_progress_cb_args() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        let seen = Rc::new(Cell::new(None));
+
+        handle.set_progress_cb(
+            seen.clone(),
+            |progress, pkgname, percent, howmany, current, seen| {
+                assert_eq!(pkgname, "pacman");
+                seen.set(Some((progress, percent, howmany, current)));
+            },
+        );
+
+        let cb = unsafe { alpm_option_get_progresscb(handle.as_ptr()) }.unwrap();
+        unsafe {
+            cb(
+                alpm_option_get_progresscb_ctx(handle.as_ptr()),
+                alpm_progress_t::ALPM_PROGRESS_UPGRADE_START,
+                c"pacman".as_ptr(),
+                50,
+                3,
+                2,
+            )
+        };
+        assert_eq!(seen.get(), Some((Progress::UpgradeStart, 50, 3, 2)));
+    }
+
+    #[test]
+    fn test_dl_progress_cb() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        let seen = Rc::new(Cell::new(None));
+
+        handle.set_dl_progress_cb(seen.clone(), |filename, downloaded, total, seen| {
+            assert_eq!(filename, "core.db");
+            seen.set(Some((downloaded, total)));
+        });
+
+        let cb = unsafe { alpm_option_get_dlcb(handle.as_ptr()) }.unwrap();
+        let ctx = unsafe { alpm_option_get_dlcb_ctx(handle.as_ptr()) };
+        let mut init = alpm_download_event_init_t { optional: 0 };
+        unsafe {
+            cb(
+                ctx,
+                c"core.db".as_ptr(),
+                alpm_download_event_type_t::ALPM_DOWNLOAD_INIT,
+                &mut init as *mut _ as *mut _,
+            )
+        };
+        assert_eq!(seen.get(), None);
+
+        let mut progress = alpm_download_event_progress_t {
+            downloaded: 10,
+            total: 40,
+        };
+        unsafe {
+            cb(
+                ctx,
+                c"core.db".as_ptr(),
+                alpm_download_event_type_t::ALPM_DOWNLOAD_PROGRESS,
+                &mut progress as *mut _ as *mut _,
+            )
+        };
+        assert_eq!(seen.get(), Some((10, 40)));
+    }
+
+    #[test]
+    fn test_question_cb_panic() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        handle.set_question_cb((), |mut question, _| {
+            question.set_answer(true);
+            panic!("question callback panicked");
+        });
+
+        let mut question = alpm_question_t {
+            any: alpm_question_any_t {
+                type_: _alpm_question_type_t::ALPM_QUESTION_REPLACE_PKG,
+                answer: 0,
+            },
+        };
+        let cb = unsafe { alpm_option_get_questioncb(handle.as_ptr()) }.unwrap();
+        let ctx = unsafe { alpm_option_get_questioncb_ctx(handle.as_ptr()) };
+        unsafe { cb(ctx, &mut question) };
+        assert_eq!(unsafe { question.any.answer }, 0);
+
+        // the callback is usable again after panicking
+        handle.set_question_cb((), |mut question, _| question.set_answer(true));
+        let cb = unsafe { alpm_option_get_questioncb(handle.as_ptr()) }.unwrap();
+        let ctx = unsafe { alpm_option_get_questioncb_ctx(handle.as_ptr()) };
+        unsafe { cb(ctx, &mut question) };
+        assert_eq!(unsafe { question.any.answer }, 1);
+    }
 }
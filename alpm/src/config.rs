@@ -0,0 +1,145 @@
+use crate::{Alpm, AlpmList, SigLevel, Usage};
+
+use alpm_sys::*;
+
+/// A snapshot of the options set on a handle, see [`Alpm::dump_config`].
+///
+/// This is meant for debugging, such as comparing against the configuration pacman uses on
+/// the same machine. [`Config::to_json`] gives a form that can be attached to bug reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub root: String,
+    pub dbpath: String,
+    pub lockfile: String,
+    pub gpgdir: Option<String>,
+    pub logfile: Option<String>,
+    pub cachedirs: Vec<String>,
+    pub hookdirs: Vec<String>,
+    pub architectures: Vec<String>,
+    pub noupgrades: Vec<String>,
+    pub noextracts: Vec<String>,
+    pub ignorepkgs: Vec<String>,
+    pub ignoregroups: Vec<String>,
+    pub overwrite_files: Vec<String>,
+    pub assume_installed: Vec<String>,
+    pub use_syslog: bool,
+    pub check_space: bool,
+    pub dbext: String,
+    pub parallel_downloads: u32,
+    pub default_siglevel: SigLevel,
+    pub local_file_siglevel: SigLevel,
+    pub remote_file_siglevel: SigLevel,
+    pub syncdbs: Vec<DbConfig>,
+    pub callbacks: ConfigCallbacks,
+}
+
+/// The options of a registered sync database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbConfig {
+    pub name: String,
+    pub servers: Vec<String>,
+    pub siglevel: SigLevel,
+    /// `None` if the usage could not be read.
+    pub usage: Option<Usage>,
+}
+
+/// Which callbacks are set on a handle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConfigCallbacks {
+    pub log: bool,
+    pub download: bool,
+    pub fetch: bool,
+    pub event: bool,
+    pub question: bool,
+    pub progress: bool,
+}
+
+impl Alpm {
+    /// Takes a snapshot of every option currently set on the handle.
+    pub fn dump_config(&self) -> Config {
+        let handle = self.as_ptr();
+
+        Config {
+            root: self.root().to_string(),
+            dbpath: self.dbpath().to_string(),
+            lockfile: self.lockfile().to_string(),
+            gpgdir: self.gpgdir().map(|s| s.to_string()),
+            logfile: self.logfile().map(|s| s.to_string()),
+            cachedirs: strings(self.cachedirs()),
+            hookdirs: strings(self.hookdirs()),
+            architectures: strings(self.architectures()),
+            noupgrades: strings(self.noupgrades()),
+            noextracts: strings(self.noextracts()),
+            ignorepkgs: strings(self.ignorepkgs()),
+            ignoregroups: strings(self.ignoregroups()),
+            overwrite_files: strings(self.overwrite_files()),
+            assume_installed: self
+                .assume_installed()
+                .iter()
+                .map(|d| d.to_string())
+                .collect(),
+            use_syslog: self.use_syslog(),
+            check_space: self.check_space(),
+            dbext: self.dbext().to_string(),
+            parallel_downloads: self.parallel_downloads(),
+            default_siglevel: self.default_siglevel(),
+            local_file_siglevel: self.local_file_siglevel(),
+            remote_file_siglevel: self.remote_file_siglevel(),
+            syncdbs: self
+                .syncdbs()
+                .iter()
+                .map(|db| DbConfig {
+                    name: db.name().to_string(),
+                    servers: strings(db.servers()),
+                    siglevel: db.siglevel(),
+                    usage: db.usage().ok(),
+                })
+                .collect(),
+            callbacks: unsafe {
+                ConfigCallbacks {
+                    log: alpm_option_get_logcb(handle).is_some(),
+                    download: alpm_option_get_dlcb(handle).is_some(),
+                    fetch: alpm_option_get_fetchcb(handle).is_some(),
+                    event: alpm_option_get_eventcb(handle).is_some(),
+                    question: alpm_option_get_questioncb(handle).is_some(),
+                    progress: alpm_option_get_progresscb(handle).is_some(),
+                }
+            },
+        }
+    }
+}
+
+fn strings(list: AlpmList<&str>) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump_config() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+        handle.add_ignorepkg("linux").unwrap();
+        handle.add_cachedir("/var/cache/pacman/pkg").unwrap();
+        let db = handle.register_syncdb_mut("core", SigLevel::NONE).unwrap();
+        db.add_server("https://example.com/core").unwrap();
+        handle.set_log_cb((), |_, _, _| ());
+
+        let config = handle.dump_config();
+        assert_eq!(config.root, "/");
+        assert!(config.dbpath.ends_with("tests/db/"));
+        assert_eq!(config.ignorepkgs, ["linux"]);
+        assert_eq!(config.cachedirs, ["/var/cache/pacman/pkg/"]);
+        assert_eq!(config.syncdbs.len(), 1);
+        assert_eq!(config.syncdbs[0].name, "core");
+        assert_eq!(config.syncdbs[0].servers, ["https://example.com/core"]);
+        assert!(config.callbacks.log);
+        assert!(!config.callbacks.question);
+
+        let json = config.to_json();
+        assert!(json.starts_with(r#"{"root":"/","#));
+        assert!(json.contains(r#""ignorepkgs":["linux"]"#));
+        assert!(json.contains(r#""callbacks":{"log":true,"#));
+    }
+}
@@ -104,7 +104,7 @@ impl Conflict {
 }
 
 #[repr(u32)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum FileConflictType {
     Target = ALPM_FILECONFLICT_TARGET as u32,
     Filesystem = ALPM_FILECONFLICT_FILESYSTEM as u32,
@@ -215,6 +215,12 @@ impl Drop for OwnedFileConflict {
 }
 
 impl Alpm {
+    /// Finds the conflicts between the packages in `list`, which can be any mix of sync
+    /// packages and loaded package files.
+    ///
+    /// Only conflicts within the list are checked, not against installed packages, so this
+    /// can check a set of packages, such as a repository being built, before anything is
+    /// installed.
     pub fn check_conflicts<'a, L: AsAlpmList<&'a Pkg>>(
         &self,
         list: L,
@@ -231,6 +237,29 @@ mod tests {
     use super::*;
     use crate::SigLevel;
 
+    #[test]
+    fn test_conflicts_free() {
+        // the packages are only borrowed so they are never read here
+        let conflict = malloc_value(alpm_conflict_t {
+            package1: std::ptr::null_mut(),
+            package2: std::ptr::null_mut(),
+            reason: malloc_dep("foo<2"),
+        });
+        let conflict = unsafe { OwnedConflict::from_ptr(conflict) };
+        assert_eq!(conflict.reason().to_string(), "foo<2");
+
+        let conflict = malloc_value(alpm_fileconflict_t {
+            target: malloc_str("foo"),
+            type_: ALPM_FILECONFLICT_TARGET,
+            file: malloc_str("/usr/bin/foo"),
+            ctarget: malloc_str("bar"),
+        });
+        let conflict = unsafe { OwnedFileConflict::from_ptr(conflict) };
+        assert_eq!(conflict.target(), "foo");
+        assert_eq!(conflict.conflict_type(), FileConflictType::Target);
+        assert_eq!(conflict.file(), "/usr/bin/foo");
+    }
+
     #[test]
     fn test_check_conflicts() {
         let handle = Alpm::new("/", "tests/db").unwrap();
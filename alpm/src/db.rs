@@ -1,13 +1,18 @@
+use crate::alpm::check_siglevel;
 use crate::utils::*;
 use crate::{
     Alpm, AlpmList, AlpmListMut, AsAlpmList, Error, Group, Package, Result, SigLevel, Usage,
 };
 
 use std::cell::UnsafeCell;
+use std::collections::HashMap;
 use std::ffi::CString;
 use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
 use std::ops::Deref;
 use std::os::raw::c_int;
+use std::path::PathBuf;
 
 use alpm_sys::*;
 
@@ -23,6 +28,41 @@ impl fmt::Debug for Db {
     }
 }
 
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Ord, PartialOrd, Hash)]
+pub enum DbCompression {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+    Lz4,
+    Lzip,
+    Unknown,
+}
+
+impl DbCompression {
+    fn from_magic(magic: &[u8]) -> DbCompression {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            DbCompression::Gzip
+        } else if magic.starts_with(b"BZh") {
+            DbCompression::Bzip2
+        } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            DbCompression::Xz
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            DbCompression::Zstd
+        } else if magic.starts_with(&[0x04, 0x22, 0x4d, 0x18]) {
+            DbCompression::Lz4
+        } else if magic.starts_with(b"LZIP") {
+            DbCompression::Lzip
+        } else if magic.get(257..262) == Some(b"ustar") || magic.iter().all(|&b| b == 0) {
+            // an empty database is just the tar end of archive marker
+            DbCompression::None
+        } else {
+            DbCompression::Unknown
+        }
+    }
+}
+
 pub struct DbMut<'h> {
     pub(crate) inner: &'h Db,
 }
@@ -43,6 +83,7 @@ impl<'h> Deref for DbMut<'h> {
 
 impl Alpm {
     pub fn register_syncdb<S: Into<Vec<u8>>>(&self, name: S, sig_level: SigLevel) -> Result<&Db> {
+        check_siglevel(sig_level)?;
         let name = CString::new(name).unwrap();
 
         let db =
@@ -95,10 +136,50 @@ impl<'h> DbMut<'h> {
         let ret = unsafe { alpm_db_remove_server(self.as_ptr(), server.as_ptr()) };
         self.check_ret(ret)
     }
+
+    /// Moves `server` to the front of the server list, so it is tried first.
+    ///
+    /// Returns false, leaving the list unchanged, if the database does not have the server.
+    pub fn move_server_to_front<S: Into<Vec<u8>>>(&self, server: S) -> Result<bool> {
+        self.move_server(server, |servers, server| servers.insert(0, server))
+    }
+
+    /// Moves `server` to the end of the server list, so it is tried last, such as after a
+    /// download from it failed.
+    ///
+    /// libalpm tries the servers in order and does not report which one a download came from,
+    /// so there is no way to tell which server was last used successfully.
+    /// [`DbStats::first_server`](crate::DbStats::first_server) records the server that was tried first
+    /// instead, and a [`Fetcher`](crate::Fetcher) is given the full URL of each download.
+    ///
+    /// Returns false, leaving the list unchanged, if the database does not have the server.
+    pub fn move_server_to_end<S: Into<Vec<u8>>>(&self, server: S) -> Result<bool> {
+        self.move_server(server, |servers, server| servers.push(server))
+    }
+
+    fn move_server<S, F>(&self, server: S, f: F) -> Result<bool>
+    where
+        S: Into<Vec<u8>>,
+        F: FnOnce(&mut Vec<String>, String),
+    {
+        let server = server.into();
+        let mut servers = self.servers().iter().map(String::from).collect::<Vec<_>>();
+        let Some(pos) = servers.iter().position(|s| s.as_bytes() == server) else {
+            return Ok(false);
+        };
+
+        let server = servers.remove(pos);
+        f(&mut servers, server);
+        self.set_servers(servers.iter())?;
+        Ok(true)
+    }
 }
 
 impl Db {
-    pub(crate) unsafe fn from_ptr<'a>(db: *mut alpm_db_t) -> &'a Db {
+    /// # Safety
+    ///
+    /// `db` must be a valid database that outlives the returned reference.
+    pub unsafe fn from_ptr<'a>(db: *mut alpm_db_t) -> &'a Db {
         &*(db as *mut Db)
     }
 
@@ -135,6 +216,40 @@ impl Db {
         unsafe { from_cstr(name) }
     }
 
+    pub fn is_local(&self) -> bool {
+        unsafe { alpm_get_localdb(self.handle_ptr()) == self.as_ptr() }
+    }
+
+    /// The path of the database on disk.
+    ///
+    /// For the local database this is a directory, for sync databases it is the database
+    /// file, which may not exist if the database has not been downloaded.
+    pub fn path(&self) -> PathBuf {
+        let handle = self.handle_ptr();
+        let dbpath = unsafe { from_cstr(alpm_option_get_dbpath(handle)) };
+        let path = PathBuf::from(dbpath);
+
+        if self.is_local() {
+            path.join("local")
+        } else {
+            let dbext = unsafe { from_cstr(alpm_option_get_dbext(handle)) };
+            path.join("sync").join(format!("{}{}", self.name(), dbext))
+        }
+    }
+
+    /// Detects the compression of the sync database file.
+    ///
+    /// Returns [`DbCompression::None`] for the local database.
+    pub fn compression(&self) -> io::Result<DbCompression> {
+        if self.is_local() {
+            return Ok(DbCompression::None);
+        }
+
+        let mut magic = Vec::with_capacity(512);
+        File::open(self.path())?.take(512).read_to_end(&mut magic)?;
+        Ok(DbCompression::from_magic(&magic))
+    }
+
     pub fn servers(&self) -> AlpmList<&str> {
         let list = unsafe { alpm_db_get_servers(self.as_ptr()) };
         unsafe { AlpmList::from_ptr(list) }
@@ -147,12 +262,64 @@ impl Db {
         unsafe { Ok(Package::from_ptr(pkg)) }
     }
 
+    /// Looks up many packages by name, returning them in the same order as `names`.
+    ///
+    /// When `names` is large compared to the database the packages are matched in a single
+    /// pass over the package cache, which avoids converting every name to a C string and
+    /// hashing it in libalpm.
+    pub fn pkgs_info<S: AsRef<str>>(&self, names: &[S]) -> Vec<Option<&Package>> {
+        let pkgs = self.pkgs();
+        if names.len() * 8 < pkgs.len() {
+            return names
+                .iter()
+                .map(|name| self.pkg(name.as_ref()).ok())
+                .collect();
+        }
+
+        let mut wanted = HashMap::<&str, Vec<usize>>::new();
+        for (i, name) in names.iter().enumerate() {
+            wanted.entry(name.as_ref()).or_default().push(i);
+        }
+
+        let mut ret = vec![None; names.len()];
+        for pkg in pkgs {
+            if let Some(indexes) = wanted.get(pkg.name()) {
+                for &i in indexes {
+                    ret[i] = Some(pkg);
+                }
+            }
+        }
+        ret
+    }
+
+    /// The packages in the database.
+    ///
+    /// The order is not guaranteed, use [`AlpmList::sorted`] if it matters.
     #[doc(alias = "pkgcache")]
     pub fn pkgs(&self) -> AlpmList<&Package> {
         let pkgs = unsafe { alpm_db_get_pkgcache(self.as_ptr()) };
         unsafe { AlpmList::from_ptr(pkgs) }
     }
 
+    /// Like [`Db::pkgs`] but returns an error if the database could not be loaded.
+    ///
+    /// libalpm returns an empty package cache for both an empty database and one that failed
+    /// to load, so the two are told apart using the handle's last error.
+    pub fn load_pkgs(&self) -> Result<AlpmList<'_, &Package>> {
+        // looking up an empty name fails with WrongArgs before the database is loaded, which
+        // replaces any error left by an earlier call
+        let empty = CString::default();
+        unsafe { alpm_db_get_pkg(self.as_ptr(), empty.as_ptr()) };
+        let pkgs = unsafe { alpm_db_get_pkgcache(self.as_ptr()) };
+        if pkgs.is_null() {
+            let err = self.last_error();
+            if let Error::DbOpen | Error::DbInvalid | Error::DbNotFound | Error::Libarchive = err {
+                return Err(err);
+            }
+        }
+        unsafe { Ok(AlpmList::from_ptr(pkgs)) }
+    }
+
     pub fn group<S: Into<Vec<u8>>>(&self, name: S) -> Result<&Group> {
         let name = CString::new(name).unwrap();
         let group = unsafe { alpm_db_get_group(self.as_ptr(), name.as_ptr()) };
@@ -165,6 +332,10 @@ impl Db {
         self.check_ret(ret)
     }
 
+    /// Packages whose name, description or provides match all of the regexes in `list`.
+    ///
+    /// The order is not guaranteed, use [`AlpmList::sorted`] on [`AlpmListMut::list`] if it
+    /// matters.
     pub fn search<'a, L>(&'a self, list: L) -> Result<AlpmListMut<&'a Package>>
     where
         L: AsAlpmList<&'a str>,
@@ -177,6 +348,10 @@ impl Db {
         })
     }
 
+    /// The groups in the database, built from the packages the first time it is called.
+    ///
+    /// The cache is rebuilt after the database is updated, see
+    /// [`update`](AlpmList::update).
     #[doc(alias = "groupcache")]
     pub fn groups(&self) -> Result<AlpmList<&Group>> {
         let groups = unsafe { alpm_db_get_groupcache(self.as_ptr()) };
@@ -208,7 +383,9 @@ impl Db {
 #[cfg(test)]
 mod tests {
     use crate::SigLevel;
-    use crate::{Alpm, AlpmListMut};
+    use crate::{Alpm, AlpmListMut, DbCompression, Usage};
+    use proptest::prelude::*;
+    use std::path::PathBuf;
 
     #[test]
     fn test_register() {
@@ -245,6 +422,48 @@ mod tests {
         assert_eq!(servers, db.servers().iter().collect::<Vec<_>>());
     }
 
+    #[test]
+    fn test_path() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        let db = handle.register_syncdb("core", SigLevel::NONE).unwrap();
+        assert!(!db.is_local());
+        assert!(handle.localdb().is_local());
+        assert_eq!(db.path(), PathBuf::from("tests/db/sync/core.db"));
+        assert_eq!(handle.localdb().path(), PathBuf::from("tests/db/local"));
+        assert_eq!(db.compression().unwrap(), DbCompression::Gzip);
+        assert_eq!(handle.localdb().compression().unwrap(), DbCompression::None);
+
+        let db = handle.register_syncdb("missing", SigLevel::NONE).unwrap();
+        assert!(db.compression().is_err());
+    }
+
+    #[test]
+    fn test_db_compression() {
+        let mut tar = [0; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(DbCompression::from_magic(&tar), DbCompression::None);
+        assert_eq!(DbCompression::from_magic(&[0; 1024]), DbCompression::None);
+        assert_eq!(
+            DbCompression::from_magic(&[0x28, 0xb5, 0x2f, 0xfd, 0]),
+            DbCompression::Zstd
+        );
+        assert_eq!(DbCompression::from_magic(b"hello"), DbCompression::Unknown);
+    }
+
+    #[test]
+    fn test_move_server() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+        let db = handle.register_syncdb_mut("foo", SigLevel::NONE).unwrap();
+        db.set_servers(["a", "b", "c"].iter().cloned()).unwrap();
+
+        assert!(db.move_server_to_end("a").unwrap());
+        assert_eq!(db.servers().iter().collect::<Vec<_>>(), ["b", "c", "a"]);
+        assert!(db.move_server_to_front("c").unwrap());
+        assert_eq!(db.servers().iter().collect::<Vec<_>>(), ["c", "b", "a"]);
+        assert!(!db.move_server_to_end("d").unwrap());
+        assert_eq!(db.servers().iter().collect::<Vec<_>>(), ["c", "b", "a"]);
+    }
+
     #[test]
     fn test_set_servers() {
         let mut handle = Alpm::new("/", "tests/db").unwrap();
@@ -257,6 +476,19 @@ mod tests {
         assert_eq!(servers, db.servers().iter().collect::<Vec<_>>());
     }
 
+    proptest! {
+        #[test]
+        fn prop_usage_round_trip(bits in any::<u32>()) {
+            let usage = Usage::from_bits_truncate(bits);
+            prop_assert_eq!(Usage::from_bits(usage.bits()), Some(usage));
+
+            let mut handle = Alpm::new("/", "tests/db").unwrap();
+            let db = handle.register_syncdb_mut("core", SigLevel::NONE).unwrap();
+            db.set_usage(usage).unwrap();
+            prop_assert_eq!(db.usage().unwrap(), usage);
+        }
+    }
+
     #[test]
     fn test_mut() {
         let mut handle = Alpm::new("/", "tests/db").unwrap();
@@ -290,6 +522,25 @@ mod tests {
         assert!(pkg.version().as_str() == "5.1.8.arch1-1");
     }
 
+    #[test]
+    fn test_pkgs_info() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        let db = handle.register_syncdb("core", SigLevel::NONE).unwrap();
+
+        let names = ["linux", "doesnotexist", "pacman", "linux"];
+        let pkgs = db.pkgs_info(&names);
+        let pkgs = pkgs.iter().map(|p| p.map(|p| p.name())).collect::<Vec<_>>();
+        assert_eq!(pkgs, [Some("linux"), None, Some("pacman"), Some("linux")]);
+
+        let names = db.pkgs().iter().map(|p| p.name()).collect::<Vec<_>>();
+        let pkgs = db.pkgs_info(&names);
+        assert!(pkgs
+            .iter()
+            .zip(&names)
+            .all(|(p, n)| p.unwrap().name() == *n));
+        assert!(db.pkgs_info::<&str>(&[]).is_empty());
+    }
+
     #[test]
     fn test_search() {
         let handle = Alpm::new("/", "tests/db").unwrap();
@@ -323,5 +574,10 @@ mod tests {
         assert_eq!(base.name(), "base");
         assert!(base.packages().len() > 10);
         assert!(base.packages().len() < 100);
+        assert_eq!(base.len(), base.packages().len());
+        assert!(!base.is_empty());
+        assert_eq!(base.pkg("pacman").unwrap().name(), "pacman");
+        assert!(base.contains("pacman"));
+        assert!(!base.contains("linux-headers"));
     }
 }
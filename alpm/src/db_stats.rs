@@ -0,0 +1,363 @@
+use crate::{Alpm, AlpmList, DbMut, Error, Result};
+
+use alpm_sys::alpm_handle_t;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The update history of a sync database, see [`Alpm::db_stats`].
+///
+/// libalpm does not keep any of this, so it only covers updates made through this crate since
+/// the handle was created or the stats were loaded with [`Alpm::load_db_stats`].
+///
+/// libalpm also returns one result for all of the databases updated together. When that is an
+/// error, databases whose file changed are counted as updated and the rest get the error, even
+/// if they were only already up to date.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DbStats {
+    /// When the database was last updated, successfully or not.
+    pub last_attempt: Option<SystemTime>,
+    /// When the database was last updated successfully.
+    pub last_success: Option<SystemTime>,
+    /// The error of the last update, if it failed.
+    pub last_error: Option<Error>,
+    /// The first server of the database at the last update.
+    ///
+    /// libalpm tries the servers in order and does not report which one the database was
+    /// downloaded from, so this is the server that was tried first, which may not be the one
+    /// that was used.
+    pub first_server: Option<String>,
+}
+
+// Like the metrics, this is keyed by the handle because db updates only have the raw handle.
+thread_local! {
+    static STATS: RefCell<HashMap<usize, HashMap<String, DbStats>>> = RefCell::new(HashMap::new());
+}
+
+/// When each database file was last changed, taken before an update so
+/// [`record_update`] can tell which databases were downloaded.
+pub(crate) fn db_file_changes(dbs: &AlpmList<DbMut<'_>>) -> Vec<Option<(i64, i64)>> {
+    dbs.iter()
+        .map(|db| {
+            // the ctime, as libalpm sets the mtime to the server's Last-Modified
+            fs::metadata(db.path())
+                .ok()
+                .map(|meta| (meta.ctime(), meta.ctime_nsec()))
+        })
+        .collect()
+}
+
+// alpm_db_update returns one result for all of the databases. When it fails, the databases
+// whose file changed were downloaded, the rest are given the error. This includes
+// databases that failed to download and those that were already up to date.
+pub(crate) fn record_update(
+    dbs: &AlpmList<DbMut<'_>>,
+    before: &[Option<(i64, i64)>],
+    ret: &Result<bool>,
+) {
+    let Some(first) = dbs.first() else {
+        return;
+    };
+    let now = SystemTime::now();
+    let after = db_file_changes(dbs);
+
+    let _ = STATS.try_with(|stats| {
+        let mut stats = stats.borrow_mut();
+        let stats = stats.entry(first.handle_ptr() as usize).or_default();
+
+        for (i, db) in dbs.iter().enumerate() {
+            let entry = stats.entry(db.name().to_string()).or_default();
+            entry.last_attempt = Some(now);
+            entry.first_server = db.servers().first().map(|s| s.to_string());
+            let downloaded = after[i].is_some() && after[i] != before[i];
+            match ret {
+                Err(e) if !downloaded => entry.last_error = Some(*e),
+                _ => {
+                    entry.last_success = Some(now);
+                    entry.last_error = None;
+                }
+            }
+        }
+    });
+}
+
+pub(crate) fn remove_db_stats(handle: *mut alpm_handle_t) {
+    let _ = STATS.try_with(|stats| stats.borrow_mut().remove(&(handle as usize)));
+}
+
+impl Alpm {
+    /// The update history of the database `name`.
+    ///
+    /// Databases that were never updated have empty stats.
+    pub fn db_stats(&self, name: &str) -> DbStats {
+        STATS.with(|stats| {
+            stats
+                .borrow()
+                .get(&(self.as_ptr() as usize))
+                .and_then(|stats| stats.get(name))
+                .cloned()
+                .unwrap_or_default()
+        })
+    }
+
+    /// Writes the stats of every database to `path`, for [`load_db_stats`](Alpm::load_db_stats)
+    /// to read back.
+    pub fn save_db_stats<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = Vec::new();
+
+        STATS.with(|stats| {
+            let stats = stats.borrow();
+            let Some(stats) = stats.get(&(self.as_ptr() as usize)) else {
+                return Ok::<_, io::Error>(());
+            };
+            let mut names = stats.keys().collect::<Vec<_>>();
+            names.sort();
+
+            for name in names {
+                let db = &stats[name];
+                writeln!(
+                    out,
+                    "{}\t{}\t{}\t{}\t{}",
+                    name,
+                    time(db.last_attempt),
+                    time(db.last_success),
+                    db.last_error
+                        .map_or("-".to_string(), |e| format!("{:?}", e)),
+                    db.first_server.as_deref().unwrap_or("-"),
+                )?;
+            }
+            Ok(())
+        })?;
+
+        fs::write(path, out)
+    }
+
+    /// Reads stats written by [`save_db_stats`](Alpm::save_db_stats), replacing the stats of
+    /// the databases in the file.
+    ///
+    /// A missing file is not an error so the same path can be used on the first run.
+    pub fn load_db_stats<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let mut loaded = Vec::new();
+        for line in data.lines() {
+            loaded.push(parse_line(line).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "invalid db stats line")
+            })?);
+        }
+
+        STATS.with(|stats| {
+            let mut stats = stats.borrow_mut();
+            let stats = stats.entry(self.as_ptr() as usize).or_default();
+            stats.extend(loaded);
+        });
+
+        Ok(())
+    }
+}
+
+fn time(time: Option<SystemTime>) -> String {
+    time.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or("-".to_string(), |d| d.as_nanos().to_string())
+}
+
+fn optional(field: &str) -> Option<Option<&str>> {
+    match field {
+        "-" => Some(None),
+        "" => None,
+        s => Some(Some(s)),
+    }
+}
+
+fn parse_time(field: &str) -> Option<Option<SystemTime>> {
+    match optional(field)? {
+        Some(nanos) => {
+            let nanos = nanos.parse().ok()?;
+            Some(Some(UNIX_EPOCH + Duration::from_nanos(nanos)))
+        }
+        None => Some(None),
+    }
+}
+
+// Errors are saved by name as the errno values change between libalpm versions.
+fn parse_error(field: &str) -> Option<Option<Error>> {
+    let err = match optional(field)? {
+        Some(name) => name,
+        None => return Some(None),
+    };
+
+    let err = match err {
+        "Ok" => Error::Ok,
+        "Memory" => Error::Memory,
+        "System" => Error::System,
+        "BadPerms" => Error::BadPerms,
+        "NotAFile" => Error::NotAFile,
+        "NotADir" => Error::NotADir,
+        "WrongArgs" => Error::WrongArgs,
+        "DiskSpace" => Error::DiskSpace,
+        "HandleNull" => Error::HandleNull,
+        "HandleNotNull" => Error::HandleNotNull,
+        "HandleLock" => Error::HandleLock,
+        "DbOpen" => Error::DbOpen,
+        "DbCreate" => Error::DbCreate,
+        "DbNull" => Error::DbNull,
+        "DbNotNull" => Error::DbNotNull,
+        "DbNotFound" => Error::DbNotFound,
+        "DbInvalid" => Error::DbInvalid,
+        "DbInvalidSig" => Error::DbInvalidSig,
+        "DbVersion" => Error::DbVersion,
+        "DbWrite" => Error::DbWrite,
+        "DbRemove" => Error::DbRemove,
+        "ServerBadUrl" => Error::ServerBadUrl,
+        "ServerNone" => Error::ServerNone,
+        "TransNotNull" => Error::TransNotNull,
+        "TransNull" => Error::TransNull,
+        "TransDupTarget" => Error::TransDupTarget,
+        "TransDupFileName" => Error::TransDupFileName,
+        "TransNotInitialized" => Error::TransNotInitialized,
+        "TransNotPrepared" => Error::TransNotPrepared,
+        "TransAbort" => Error::TransAbort,
+        "TransType" => Error::TransType,
+        "TransNotLocked" => Error::TransNotLocked,
+        "TransHookFailed" => Error::TransHookFailed,
+        "PkgNotFound" => Error::PkgNotFound,
+        "PkgIgnored" => Error::PkgIgnored,
+        "PkgInvalid" => Error::PkgInvalid,
+        "PkgInvalidChecksum" => Error::PkgInvalidChecksum,
+        "PkgInvalidSig" => Error::PkgInvalidSig,
+        "PkgMissingSig" => Error::PkgMissingSig,
+        "PkgOpen" => Error::PkgOpen,
+        "PkgCantRemove" => Error::PkgCantRemove,
+        "PkgInvalidName" => Error::PkgInvalidName,
+        "PkgInvalidArch" => Error::PkgInvalidArch,
+        "SigMissing" => Error::SigMissing,
+        "SigInvalid" => Error::SigInvalid,
+        "UnsatisfiedDeps" => Error::UnsatisfiedDeps,
+        "ConflictingDeps" => Error::ConflictingDeps,
+        "FileConflicts" => Error::FileConflicts,
+        "Retrieve" => Error::Retrieve,
+        "InvalidRegex" => Error::InvalidRegex,
+        "Libarchive" => Error::Libarchive,
+        "Libcurl" => Error::Libcurl,
+        "ExternalDownload" => Error::ExternalDownload,
+        "Gpgme" => Error::Gpgme,
+        "MissingCapabilitySignatures" => Error::MissingCapabilitySignatures,
+        "InCallback" => Error::InCallback,
+        "UnsupportedByLibalpm" => Error::UnsupportedByLibalpm,
+        "Timeout" => Error::Timeout,
+        _ => return None,
+    };
+    Some(Some(err))
+}
+
+fn parse_line(line: &str) -> Option<(String, DbStats)> {
+    let mut fields = line.split('\t');
+    let name = fields.next().filter(|s| !s.is_empty())?;
+    let stats = DbStats {
+        last_attempt: parse_time(fields.next()?)?,
+        last_success: parse_time(fields.next()?)?,
+        last_error: parse_error(fields.next()?)?,
+        first_server: optional(fields.next()?)?.map(|s| s.to_string()),
+    };
+
+    if fields.next().is_some() {
+        return None;
+    }
+
+    Some((name.to_string(), stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SigLevel;
+
+    #[test]
+    fn test_parse_line() {
+        let (name, stats) =
+            parse_line("core\t1000000001\t-\tServerNone\thttps://example.com").unwrap();
+        assert_eq!(name, "core");
+        assert_eq!(stats.last_attempt, Some(UNIX_EPOCH + Duration::new(1, 1)));
+        assert_eq!(stats.last_success, None);
+        assert_eq!(stats.last_error, Some(Error::ServerNone));
+        assert_eq!(stats.first_server.as_deref(), Some("https://example.com"));
+
+        assert!(parse_line("core\t-\t-\t-").is_none());
+        assert!(parse_line("core\t-\t-\t22\t-").is_none());
+        assert!(parse_line("core\tx\t-\t-\t-").is_none());
+    }
+
+    #[test]
+    fn test_error_names() {
+        let errors = (0..=Error::MissingCapabilitySignatures as u32)
+            .map(|code| unsafe { std::mem::transmute::<u32, Error>(code) })
+            .chain([
+                Error::InCallback,
+                Error::UnsupportedByLibalpm,
+                Error::Timeout,
+            ]);
+
+        for err in errors {
+            assert_eq!(parse_error(&format!("{:?}", err)), Some(Some(err)));
+        }
+        assert_eq!(parse_error("NotAnError"), None);
+    }
+
+    #[test]
+    fn test_record_partial_update() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("sync")).unwrap();
+        let mut handle = Alpm::new("/", dir.to_str().unwrap()).unwrap();
+        handle.register_syncdb_mut("core", SigLevel::NONE).unwrap();
+        handle.register_syncdb_mut("extra", SigLevel::NONE).unwrap();
+
+        // core was downloaded before extra failed
+        let dbs = handle.syncdbs_mut();
+        let before = db_file_changes(&dbs);
+        fs::write(dir.join("sync/core.db"), "").unwrap();
+        record_update(&dbs, &before, &Err(Error::Retrieve));
+
+        let core = handle.db_stats("core");
+        assert!(core.last_success.is_some());
+        assert_eq!(core.last_error, None);
+        let extra = handle.db_stats("extra");
+        assert_eq!(extra.last_success, None);
+        assert_eq!(extra.last_error, Some(Error::Retrieve));
+    }
+
+    #[test]
+    fn test_db_stats() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+        handle.register_syncdb_mut("core", SigLevel::NONE).unwrap();
+        assert_eq!(handle.db_stats("core"), DbStats::default());
+
+        // no servers are set so the update fails
+        let err = handle.syncdbs_mut().update(false).unwrap_err();
+        let stats = handle.db_stats("core");
+        assert!(stats.last_attempt.is_some());
+        assert_eq!(stats.last_success, None);
+        assert_eq!(stats.last_error, Some(err));
+        assert_eq!(stats.first_server, None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db-stats");
+        handle.save_db_stats(&path).unwrap();
+        drop(handle);
+
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        handle.load_db_stats(&path).unwrap();
+        assert_eq!(handle.db_stats("core"), stats);
+        fs::remove_file(&path).unwrap();
+        handle.load_db_stats(&path).unwrap();
+    }
+}
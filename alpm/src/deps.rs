@@ -1,3 +1,4 @@
+use crate::instrument::{track_free, track_new, Kind};
 use crate::utils::*;
 use crate::{free, Alpm, AlpmList, AlpmListMut, AsAlpmList, Db, Package, Pkg, Ver};
 
@@ -33,6 +34,9 @@ pub struct Depend {
     dep: NonNull<alpm_depend_t>,
 }
 
+unsafe impl Send for Depend {}
+unsafe impl Sync for Depend {}
+
 impl PartialEq for Depend {
     fn eq(&self, other: &Self) -> bool {
         self.as_dep() == other.as_dep()
@@ -52,6 +56,7 @@ impl Clone for Depend {
         let dep = unsafe { alpm_dep_from_string(ptr) };
         unsafe { free(ptr as _) };
         assert!(!dep.is_null(), "failed to create dep from string");
+        track_new(Kind::Depend);
         unsafe { Depend::from_ptr(dep) }
     }
 }
@@ -79,6 +84,7 @@ impl std::ops::Deref for Depend {
 impl Drop for Depend {
     fn drop(&mut self) {
         unsafe { alpm_dep_free(self.dep.as_ptr()) }
+        track_free(Kind::Depend);
     }
 }
 
@@ -122,6 +128,7 @@ impl Depend {
         let s = CString::new(s).unwrap();
         let dep = unsafe { alpm_dep_from_string(s.as_ptr()) };
         assert!(!dep.is_null(), "failed to create dep from string");
+        track_new(Kind::Depend);
         unsafe {
             Depend {
                 dep: NonNull::new_unchecked(dep),
@@ -168,7 +175,12 @@ impl Dep {
     }
 
     pub fn version(&self) -> Option<&Ver> {
-        unsafe { (*self.as_ptr()).version.as_ref().map(|p| Ver::from_ptr(p)) }
+        let version = unsafe { (*self.as_ptr()).version };
+        if version.is_null() {
+            None
+        } else {
+            unsafe { Some(Ver::from_ptr(version)) }
+        }
     }
 
     unsafe fn version_unchecked(&self) -> &Ver {
@@ -407,6 +419,30 @@ mod tests {
         assert_eq!(dep.version().unwrap().as_str(), "3");
     }
 
+    #[test]
+    fn test_depend_string() {
+        let dep = Depend::new("foo>=1.2: for bar");
+        assert_eq!(dep.name(), "foo");
+        assert_eq!(dep.depmod(), DepMod::Ge);
+        assert_eq!(dep.version().unwrap().as_str(), "1.2");
+        assert_eq!(dep.desc(), Some("for bar"));
+        assert_eq!(dep.to_string(), "foo>=1.2: for bar");
+        assert_eq!(dep.clone().to_string(), dep.to_string());
+    }
+
+    #[test]
+    fn test_depend_missing_free() {
+        let miss = malloc_value(alpm_depmissing_t {
+            target: malloc_str("foo"),
+            depend: malloc_dep("bar=1"),
+            causingpkg: malloc_str("baz"),
+        });
+        let miss = unsafe { DependMissing::from_ptr(miss) };
+        assert_eq!(miss.target(), "foo");
+        assert_eq!(miss.depend().to_string(), "bar=1");
+        assert_eq!(miss.causing_pkg(), Some("baz"));
+    }
+
     #[test]
     fn test_depend_lifetime() {
         let handle = Alpm::new("/", "tests/db").unwrap();
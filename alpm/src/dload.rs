@@ -1,17 +1,425 @@
-use crate::{Alpm, AlpmListMut, AsAlpmList, Result};
+use crate::{
+    Alpm, AlpmListMut, AnyDownloadEvent, AsAlpmList, DownloadEvent, Error, FetchResult, Result,
+};
 
 use alpm_sys::*;
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ptr;
+use std::time::{Duration, Instant};
 
 impl Alpm {
     pub fn fetch_pkgurl<'a, L: AsAlpmList<&'a str>>(&self, urls: L) -> Result<AlpmListMut<String>> {
+        if !self.supports_downloads() {
+            return Err(Error::UnsupportedByLibalpm);
+        }
+
         urls.with(|url| {
             let mut out = ptr::null_mut();
+            start_fetch_timeout(self.as_ptr());
             let ret = unsafe { alpm_fetch_pkgurl(self.as_ptr(), url.as_ptr(), &mut out) };
-            self.check_ret(ret)?;
+            finish_fetch_timeout(self.as_ptr(), self.check_ret(ret))?;
             let fetched = unsafe { AlpmListMut::from_ptr(out) };
             Ok(fetched)
         })
     }
+
+    /// Limits how long a db update, [`fetch_pkgurl`](Alpm::fetch_pkgurl) or
+    /// [`trans_commit`](Alpm::trans_commit) may spend downloading, or removes the limit with
+    /// `None`.
+    ///
+    /// Once the time is up, downloads still to come fail without calling the fetch callback,
+    /// a download that finished late counts as failed and the operation fails with
+    /// [`Error::Timeout`]. A download that is already running can not be interrupted, so the
+    /// fetcher should also limit each of its transfers.
+    ///
+    /// This only applies to downloads made through [`Alpm::set_fetcher`] or
+    /// [`Alpm::set_fetch_cb`]. libalpm's own downloader can only have its low speed timeout
+    /// turned off, see [`Alpm::set_disable_dl_timeout`].
+    pub fn set_fetch_timeout(&self, timeout: Option<Duration>) {
+        let _ = TIMEOUTS.try_with(|timeouts| {
+            let mut timeouts = timeouts.borrow_mut();
+            match timeout {
+                Some(timeout) => {
+                    timeouts.insert(
+                        self.as_ptr() as usize,
+                        FetchTimeout {
+                            timeout,
+                            deadline: None,
+                            timed_out: false,
+                        },
+                    );
+                }
+                None => {
+                    timeouts.remove(&(self.as_ptr() as usize));
+                }
+            }
+        });
+    }
+}
+
+struct FetchTimeout {
+    timeout: Duration,
+    // set while an operation that downloads is running
+    deadline: Option<Instant>,
+    timed_out: bool,
+}
+
+// Like the metrics, this is keyed by the handle because the fetch callback only has the
+// raw handle.
+thread_local! {
+    static TIMEOUTS: RefCell<HashMap<usize, FetchTimeout>> = RefCell::new(HashMap::new());
+}
+
+fn with_timeout<R>(
+    handle: *mut alpm_handle_t,
+    f: impl FnOnce(&mut FetchTimeout) -> R,
+) -> Option<R> {
+    TIMEOUTS
+        .try_with(|timeouts| timeouts.borrow_mut().get_mut(&(handle as usize)).map(f))
+        .ok()
+        .flatten()
+}
+
+/// Starts the deadline for the downloads of an operation.
+pub(crate) fn start_fetch_timeout(handle: *mut alpm_handle_t) {
+    with_timeout(handle, |t| {
+        t.deadline = Some(Instant::now() + t.timeout);
+        t.timed_out = false;
+    });
+}
+
+/// Ends the deadline started by [`start_fetch_timeout`], turning the error into
+/// [`Error::Timeout`] if a download was failed because the time was up.
+pub(crate) fn finish_fetch_timeout<T>(handle: *mut alpm_handle_t, ret: Result<T>) -> Result<T> {
+    let timed_out = with_timeout(handle, |t| {
+        t.deadline = None;
+        t.timed_out
+    });
+    match ret {
+        Err(_) if timed_out == Some(true) => Err(Error::Timeout),
+        ret => ret,
+    }
+}
+
+pub(crate) fn remove_fetch_timeout(handle: *mut alpm_handle_t) {
+    let _ = TIMEOUTS.try_with(|timeouts| timeouts.borrow_mut().remove(&(handle as usize)));
+}
+
+/// Runs a fetch callback, failing it instead if the deadline has passed.
+pub(crate) fn fetch_with_timeout(
+    handle: *mut alpm_handle_t,
+    fetch: impl FnOnce() -> FetchResult,
+) -> FetchResult {
+    let late = || {
+        with_timeout(handle, |t| {
+            let late = t
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline);
+            t.timed_out |= late;
+            late
+        })
+        .unwrap_or(false)
+    };
+
+    if late() {
+        return FetchResult::Err;
+    }
+    let ret = fetch();
+    if late() {
+        return FetchResult::Err;
+    }
+    ret
+}
+
+/// Downloads files in place of libalpm's built in downloader.
+///
+/// Set with [`Alpm::set_fetcher`]. Closures with the same arguments as [`Fetcher::fetch`] are
+/// fetchers too. A fetcher that panics is treated as returning [`FetchResult::Err`].
+pub trait Fetcher {
+    /// Downloads `url` into the directory `localpath`.
+    ///
+    /// `force` is set when the file should be downloaded even if it is already up to date.
+    /// Return [`FetchResult::FileExists`] when the file was up to date and nothing was
+    /// downloaded.
+    fn fetch(&mut self, url: &str, localpath: &str, force: bool) -> FetchResult;
+}
+
+impl<F: FnMut(&str, &str, bool) -> FetchResult> Fetcher for F {
+    fn fetch(&mut self, url: &str, localpath: &str, force: bool) -> FetchResult {
+        self(url, localpath, force)
+    }
+}
+
+impl Alpm {
+    /// Uses `fetcher` to download databases and packages.
+    ///
+    /// This replaces any callback set with [`Alpm::set_fetch_cb`].
+    pub fn set_fetcher<F: Fetcher + 'static>(&self, fetcher: F) {
+        self.set_fetch_cb(fetcher, |url, localpath, force, fetcher| {
+            fetcher.fetch(url, localpath, force)
+        });
+    }
+}
+
+/// What a file given to the download callback is.
+///
+/// The download callback is used for both database updates and package downloads, which
+/// libalpm only tells apart by the file name. Signatures are classified as the file they
+/// sign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DownloadKind<'a> {
+    /// A sync database, with the name of the database.
+    Db(&'a str),
+    /// A package, with the name of the package.
+    Package(&'a str),
+}
+
+impl<'a> DownloadKind<'a> {
+    /// Works out what is being downloaded from a file name passed to the download callback.
+    pub fn from_filename(filename: &'a str) -> DownloadKind<'a> {
+        let file = filename.strip_suffix(".sig").unwrap_or(filename);
+
+        if let Some(i) = file.find(".pkg.tar") {
+            // name-pkgver-pkgrel-arch
+            let stem = &file[..i];
+            let name = stem.rsplitn(4, '-').nth(3).unwrap_or(stem);
+            DownloadKind::Package(name)
+        } else {
+            let name = file.rsplit_once('.').map_or(file, |(name, _)| name);
+            DownloadKind::Db(name)
+        }
+    }
+
+    /// The name of the database or package.
+    pub fn name(&self) -> &'a str {
+        match self {
+            DownloadKind::Db(name) | DownloadKind::Package(name) => name,
+        }
+    }
+}
+
+/// Estimates the combined speed of all running downloads from download callback events.
+///
+/// The speed is an exponential moving average so it does not jump around with every
+/// progress event. `smoothing` is how long it takes an older sample to lose most of its
+/// weight; larger values give a steadier but slower to react figure.
+#[derive(Debug, Clone)]
+pub struct DownloadRate {
+    smoothing: Duration,
+    interval: Duration,
+    files: HashMap<String, i64>,
+    pending: u64,
+    last: Option<Instant>,
+    rate: Option<f64>,
+}
+
+impl Default for DownloadRate {
+    fn default() -> Self {
+        DownloadRate::new(Duration::from_secs(2))
+    }
+}
+
+impl DownloadRate {
+    pub fn new(smoothing: Duration) -> Self {
+        DownloadRate {
+            smoothing,
+            interval: smoothing / 10,
+            files: HashMap::new(),
+            pending: 0,
+            last: None,
+            rate: None,
+        }
+    }
+
+    /// The estimated download speed in bytes per second.
+    pub fn rate(&self) -> f64 {
+        self.rate.unwrap_or(0.0)
+    }
+
+    /// Records a download event for `filename`.
+    pub fn update(&mut self, filename: &str, event: &AnyDownloadEvent) {
+        self.update_at(filename, event.event(), Instant::now())
+    }
+
+    /// Records a download event for `filename` that happened at `now`.
+    pub fn update_at(&mut self, filename: &str, event: DownloadEvent, now: Instant) {
+        match event {
+            DownloadEvent::Init(_) => {
+                self.files.insert(filename.to_string(), 0);
+            }
+            DownloadEvent::Retry(retry) => {
+                if !retry.resume {
+                    self.files.insert(filename.to_string(), 0);
+                }
+            }
+            DownloadEvent::Progress(progress) => {
+                let prev = self.files.entry(filename.to_string()).or_insert(0);
+                self.pending += (progress.downloaded - *prev).max(0) as u64;
+                *prev = progress.downloaded;
+            }
+            DownloadEvent::Completed(_) => {
+                self.files.remove(filename);
+            }
+        }
+
+        self.tick(now);
+    }
+
+    // Samples are gathered for at least interval before being averaged in so the rate isn't
+    // computed from bursts of events that arrive at almost the same time.
+    fn tick(&mut self, now: Instant) {
+        let last = match self.last {
+            Some(last) => last,
+            None => {
+                self.last = Some(now);
+                return;
+            }
+        };
+
+        let elapsed = now.saturating_duration_since(last);
+        if elapsed < self.interval || elapsed.is_zero() {
+            return;
+        }
+
+        let sample = self.pending as f64 / elapsed.as_secs_f64();
+        let alpha = 1.0 - (-elapsed.as_secs_f64() / self.smoothing.as_secs_f64()).exp();
+        self.rate = Some(match self.rate {
+            Some(rate) => rate + alpha * (sample - rate),
+            None => sample,
+        });
+        self.pending = 0;
+        self.last = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DownloadEventInit, DownloadEventProgress};
+
+    fn progress(downloaded: i64) -> DownloadEvent {
+        DownloadEvent::Progress(DownloadEventProgress {
+            downloaded,
+            total: 1 << 30,
+        })
+    }
+
+    #[test]
+    fn test_download_kind() {
+        let kind = DownloadKind::from_filename;
+        assert_eq!(kind("core.db"), DownloadKind::Db("core"));
+        assert_eq!(kind("core.db.sig"), DownloadKind::Db("core"));
+        assert_eq!(kind("extra.files"), DownloadKind::Db("extra"));
+        assert_eq!(
+            kind("pacman-6.1.0-3-x86_64.pkg.tar.zst"),
+            DownloadKind::Package("pacman")
+        );
+        assert_eq!(
+            kind("lib32-gcc-libs-1:14.1.1+r1-1-x86_64.pkg.tar.zst.sig"),
+            DownloadKind::Package("lib32-gcc-libs")
+        );
+        assert_eq!(kind("core.db").name(), "core");
+    }
+
+    #[test]
+    fn test_download_rate() {
+        let mut rate = DownloadRate::new(Duration::from_secs(1));
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let init = DownloadEvent::Init(DownloadEventInit { optional: false });
+        rate.update_at("a", init, at(0));
+        rate.update_at("b", init, at(0));
+        assert_eq!(rate.rate(), 0.0);
+
+        // two files at 1000 bytes per second each
+        for i in 1..=100 {
+            rate.update_at("a", progress(i * 100), at(i as u64 * 100));
+            rate.update_at("b", progress(i * 100), at(i as u64 * 100));
+        }
+        assert!((rate.rate() - 2000.0).abs() < 1.0);
+
+        // a single burst moves the estimate but does not replace it
+        rate.update_at("a", progress(20_000), at(10_100));
+        assert!(rate.rate() > 2000.0);
+        assert!(rate.rate() < 100_000.0);
+    }
+
+    struct Offline(Vec<String>);
+
+    impl Fetcher for Offline {
+        fn fetch(&mut self, url: &str, _localpath: &str, _force: bool) -> FetchResult {
+            self.0.push(url.to_string());
+            match url {
+                "https://example.com/cached" => FetchResult::FileExists,
+                "https://example.com/panic" => panic!("fetcher panicked"),
+                _ => FetchResult::Err,
+            }
+        }
+    }
+
+    #[test]
+    fn test_fetch_timeout() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        let slow = || {
+            std::thread::sleep(Duration::from_millis(20));
+            FetchResult::Ok
+        };
+
+        // without a timeout nothing changes
+        start_fetch_timeout(handle.as_ptr());
+        assert_eq!(fetch_with_timeout(handle.as_ptr(), slow), FetchResult::Ok);
+        let ret = finish_fetch_timeout(handle.as_ptr(), Err::<(), _>(Error::Retrieve));
+        assert_eq!(ret, Err(Error::Retrieve));
+
+        handle.set_fetch_timeout(Some(Duration::from_millis(10)));
+        // outside of an operation the timeout does not apply
+        assert_eq!(fetch_with_timeout(handle.as_ptr(), slow), FetchResult::Ok);
+
+        start_fetch_timeout(handle.as_ptr());
+        assert_eq!(fetch_with_timeout(handle.as_ptr(), slow), FetchResult::Err);
+        let mut called = false;
+        let fetch = || {
+            called = true;
+            FetchResult::Ok
+        };
+        assert_eq!(fetch_with_timeout(handle.as_ptr(), fetch), FetchResult::Err);
+        assert!(!called);
+        let ret = finish_fetch_timeout(handle.as_ptr(), Err::<(), _>(Error::Retrieve));
+        assert_eq!(ret, Err(Error::Timeout));
+
+        start_fetch_timeout(handle.as_ptr());
+        let ret = finish_fetch_timeout(handle.as_ptr(), Err::<(), _>(Error::Retrieve));
+        assert_eq!(ret, Err(Error::Retrieve));
+    }
+
+    #[test]
+    fn test_fetcher() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        handle.set_fetcher(Offline(Vec::new()));
+        assert!(handle.supports_downloads());
+
+        let cb = unsafe { alpm_option_get_fetchcb(handle.as_ptr()) }.unwrap();
+        let ctx = unsafe { alpm_option_get_fetchcb_ctx(handle.as_ptr()) };
+        let fetch = |url: &std::ffi::CStr| unsafe { cb(ctx, url.as_ptr(), c"/tmp".as_ptr(), 0) };
+
+        assert_eq!(fetch(c"https://example.com/cached"), 1);
+        assert_eq!(fetch(c"https://example.com/missing"), -1);
+        assert_eq!(fetch(c"https://example.com/panic"), -1);
+
+        handle.set_fetcher(|_: &str, _: &str, force: bool| {
+            if force {
+                FetchResult::Ok
+            } else {
+                FetchResult::FileExists
+            }
+        });
+        let cb = unsafe { alpm_option_get_fetchcb(handle.as_ptr()) }.unwrap();
+        let ctx = unsafe { alpm_option_get_fetchcb_ctx(handle.as_ptr()) };
+        let url = c"https://example.com/core.db".as_ptr();
+        assert_eq!(unsafe { cb(ctx, url, c"/tmp".as_ptr(), 1) }, 0);
+        assert_eq!(unsafe { cb(ctx, url, c"/tmp".as_ptr(), 0) }, 1);
+    }
 }
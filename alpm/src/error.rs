@@ -11,6 +11,7 @@ use alpm_sys::*;
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[repr(u32)]
+#[non_exhaustive]
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Ord, PartialOrd, Hash)]
 pub enum Error {
     Ok = ALPM_ERR_OK as u32,
@@ -68,6 +69,14 @@ pub enum Error {
     ExternalDownload = ALPM_ERR_EXTERNAL_DOWNLOAD as u32,
     Gpgme = ALPM_ERR_GPGME as u32,
     MissingCapabilitySignatures = ALPM_ERR_MISSING_CAPABILITY_SIGNATURES as u32,
+    /// The operation is not allowed from inside of a callback.
+    InCallback = u32::MAX,
+    /// libalpm was built without a feature the operation needs, see
+    /// [`Alpm::supports_signatures`] and [`Alpm::supports_downloads`].
+    UnsupportedByLibalpm = u32::MAX - 1,
+    /// The downloads of an operation did not finish within the time set with
+    /// [`Alpm::set_fetch_timeout`].
+    Timeout = u32::MAX - 2,
 }
 
 impl Error {
@@ -77,6 +86,10 @@ impl Error {
 }
 
 impl Alpm {
+    /// The error set by the last libalpm call on the handle that failed.
+    ///
+    /// libalpm does not clear this when a call succeeds, so it is only meaningful straight
+    /// after a failure.
     pub fn last_error(&self) -> Error {
         unsafe { Error::new(alpm_errno(self.as_ptr())) }
     }
@@ -90,6 +103,15 @@ impl Error {
 
 impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if *self == Error::InCallback {
+            return fmt.write_str("operation not allowed inside of a callback");
+        }
+        if *self == Error::UnsupportedByLibalpm {
+            return fmt.write_str("operation not supported by this build of libalpm");
+        }
+        if *self == Error::Timeout {
+            return fmt.write_str("download timed out");
+        }
         let err = unsafe { transmute::<Error, alpm_errno_t>(*self) };
         let s = unsafe { CStr::from_ptr(alpm_strerror(err)) };
         fmt.write_str(s.to_str().unwrap())
@@ -100,7 +122,7 @@ impl error::Error for Error {}
 
 #[cfg(test)]
 mod tests {
-    use crate::Alpm;
+    use super::*;
 
     #[test]
     fn display() {
@@ -108,4 +130,22 @@ mod tests {
 
         println!("{}", handle.last_error());
     }
+
+    #[test]
+    fn test_display_all() {
+        let errors = (0..=Error::MissingCapabilitySignatures as u32)
+            .map(|code| unsafe { transmute::<u32, Error>(code) })
+            .chain([
+                Error::InCallback,
+                Error::UnsupportedByLibalpm,
+                Error::Timeout,
+            ]);
+
+        for err in errors {
+            let msg = err.to_string();
+            assert!(!msg.is_empty(), "{:?}", err);
+            assert_ne!(msg, "unexpected error", "{:?}", err);
+        }
+        assert_eq!(Error::Ok.to_string(), "no error");
+    }
 }
@@ -2,7 +2,6 @@ use crate::utils::*;
 
 use alpm_sys::*;
 
-use std::cell::UnsafeCell;
 use std::ffi::CString;
 use std::fmt;
 use std::marker::PhantomData;
@@ -43,14 +42,10 @@ impl File {
 }
 
 pub struct FileList<'h> {
-    inner: UnsafeCell<alpm_filelist_t>,
+    inner: alpm_filelist_t,
     _marker: PhantomData<&'h ()>,
 }
 
-// TODO: unsafe cell is only used to get a mut pointer even though
-// it's never actually mutated
-// Upstream code should ask for a const pointer
-#[allow(clippy::non_send_fields_in_send_ty)]
 unsafe impl<'h> Send for FileList<'h> {}
 unsafe impl<'h> Sync for FileList<'h> {}
 
@@ -65,17 +60,17 @@ impl<'h> fmt::Debug for FileList<'h> {
 impl<'h> FileList<'h> {
     pub(crate) unsafe fn new<'a>(files: alpm_filelist_t) -> FileList<'a> {
         FileList {
-            inner: UnsafeCell::new(files),
+            inner: files,
             _marker: PhantomData,
         }
     }
 
-    pub(crate) fn as_ptr(&self) -> *mut alpm_filelist_t {
-        self.inner.get()
+    pub(crate) fn as_ptr(&self) -> *const alpm_filelist_t {
+        &self.inner
     }
 
-    pub fn files(&self) -> &[File] {
-        let files = unsafe { *self.as_ptr() };
+    pub fn files(&self) -> &'h [File] {
+        let files = self.inner;
         if files.files.is_null() {
             &[]
         } else {
@@ -1,9 +1,15 @@
+use crate::alpm::check_siglevel;
 use crate::utils::*;
-use crate::{Alpm, AlpmList, AsAlpmList, Db, DbMut, Dep, Match, Result, SigLevel};
+use crate::{
+    fnmatch, Alpm, AlpmList, AlpmListMut, AsAlpmList, Db, DbMut, Dep, Match, Result, SigLevel,
+    Usage,
+};
 
 use alpm_sys::*;
 use std::cmp::Ordering;
 use std::ffi::CString;
+use std::fs;
+use std::io;
 
 impl Alpm {
     pub fn as_alpm_handle_t(&self) -> *mut alpm_handle_t {
@@ -15,6 +21,46 @@ impl Alpm {
         self.check_ret(ret)
     }
 
+    /// Returns true if the lock file exists but no running process has it open.
+    ///
+    /// libalpm does not record who holds the lock, so this looks through `/proc` for an open
+    /// file descriptor to it. If the open files of any process can not be read, which without
+    /// root includes those of other users, the lock may still be held and this returns false.
+    /// A stale lock can be removed with [`Alpm::unlock`].
+    pub fn lock_is_stale(&self) -> bool {
+        let lockfile = match fs::canonicalize(self.lockfile()) {
+            Ok(lockfile) => lockfile,
+            Err(_) => return false,
+        };
+        let procs = match fs::read_dir("/proc") {
+            Ok(procs) => procs,
+            Err(_) => return false,
+        };
+
+        for proc in procs.flatten() {
+            let name = proc.file_name();
+            if !name.to_string_lossy().bytes().all(|b| b.is_ascii_digit()) {
+                continue;
+            }
+
+            let fds = match fs::read_dir(proc.path().join("fd")) {
+                Ok(fds) => fds,
+                // the process has exited
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(_) => return false,
+            };
+
+            if fds
+                .flatten()
+                .any(|fd| fs::read_link(fd.path()).is_ok_and(|path| path == lockfile))
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
     pub fn root(&self) -> &str {
         unsafe { from_cstr(alpm_option_get_root(self.as_ptr())) }
     }
@@ -65,6 +111,22 @@ impl Alpm {
         unsafe { AlpmList::from_ptr(list) }
     }
 
+    /// The IgnorePkg pattern that causes packages called `name` to be ignored.
+    ///
+    /// Patterns are globs and later patterns take priority, so `None` is returned if the last
+    /// pattern to match is negated with `!`. This matches the way
+    /// [`Pkg::should_ignore`](crate::Pkg::should_ignore) checks package names.
+    pub fn match_ignore<S: Into<Vec<u8>>>(&self, name: S) -> Option<&str> {
+        match_patterns(self.ignorepkgs(), name)
+    }
+
+    /// The IgnoreGroup pattern that causes packages in `group` to be ignored.
+    ///
+    /// See [`Alpm::match_ignore`].
+    pub fn match_ignoregroup<S: Into<Vec<u8>>>(&self, group: S) -> Option<&str> {
+        match_patterns(self.ignoregroups(), group)
+    }
+
     pub fn overwrite_files(&self) -> AlpmList<'_, &str> {
         let list = unsafe { alpm_option_get_overwrite_files(self.as_ptr()) };
         unsafe { AlpmList::from_ptr(list) }
@@ -88,6 +150,9 @@ impl Alpm {
         unsafe { from_cstr(alpm_option_get_dbext(self.as_ptr())) }
     }
 
+    // The list options all take &mut self. Even adding writes the next and prev pointers of
+    // the list nodes, which an AlpmList borrowed from a getter may be reading on another
+    // thread.
     pub fn add_hookdir<S: Into<Vec<u8>>>(&mut self, s: S) -> Result<()> {
         let s = CString::new(s).unwrap();
         let ret = unsafe { alpm_option_add_hookdir(self.as_ptr(), s.as_ptr()) };
@@ -178,7 +243,7 @@ impl Alpm {
         }
     }
 
-    pub fn match_noupgrade<S: Into<Vec<u8>>>(&mut self, s: S) -> Match {
+    pub fn match_noupgrade<S: Into<Vec<u8>>>(&self, s: S) -> Match {
         let s = CString::new(s).unwrap();
         let ret = unsafe { alpm_option_match_noupgrade(self.as_ptr(), s.as_ptr()) };
 
@@ -212,7 +277,7 @@ impl Alpm {
         }
     }
 
-    pub fn match_noextract<S: Into<Vec<u8>>>(&mut self, s: S) -> Match {
+    pub fn match_noextract<S: Into<Vec<u8>>>(&self, s: S) -> Match {
         let s = CString::new(s).unwrap();
         let ret = unsafe { alpm_option_match_noextract(self.as_ptr(), s.as_ptr()) };
 
@@ -346,6 +411,14 @@ impl Alpm {
         unsafe { AlpmList::from_ptr(dbs) }
     }
 
+    /// The sync databases whose usage includes all of `usage`.
+    pub fn syncdbs_usable_for(&self, usage: Usage) -> AlpmListMut<&Db> {
+        self.syncdbs()
+            .iter()
+            .filter(|db| db.usage().is_ok_and(|u| u.contains(usage)))
+            .collect()
+    }
+
     pub fn syncdbs_mut(&mut self) -> AlpmList<DbMut> {
         let dbs = unsafe { alpm_get_syncdbs(self.as_ptr()) };
         unsafe { AlpmList::from_ptr(dbs) }
@@ -362,6 +435,7 @@ impl Alpm {
     }
 
     pub fn set_default_siglevel(&self, s: SigLevel) -> Result<()> {
+        check_siglevel(s)?;
         let ret = unsafe { alpm_option_set_default_siglevel(self.as_ptr(), s.bits() as i32) };
         self.check_ret(ret)
     }
@@ -372,6 +446,7 @@ impl Alpm {
     }
 
     pub fn set_local_file_siglevel(&self, s: SigLevel) -> Result<()> {
+        check_siglevel(s)?;
         let ret = unsafe { alpm_option_set_local_file_siglevel(self.as_ptr(), s.bits() as i32) };
         self.check_ret(ret)
     }
@@ -382,6 +457,7 @@ impl Alpm {
     }
 
     pub fn set_remote_file_siglevel(&self, s: SigLevel) -> Result<()> {
+        check_siglevel(s)?;
         let ret = unsafe { alpm_option_set_remote_file_siglevel(self.as_ptr(), s.bits() as i32) };
         self.check_ret(ret)
     }
@@ -399,11 +475,78 @@ impl Alpm {
     pub fn set_parallel_downloads(&self, n: u32) {
         unsafe { alpm_option_set_parallel_downloads(self.as_ptr(), n) };
     }
+
+    pub fn parallel_downloads(&self) -> u32 {
+        unsafe { alpm_option_get_parallel_downloads(self.as_ptr()) as u32 }
+    }
+}
+
+// Same as _alpm_fnmatch_patterns but returns the pattern that matched.
+fn match_patterns<'a, S: Into<Vec<u8>>>(patterns: AlpmList<'a, &'a str>, s: S) -> Option<&'a str> {
+    let s = CString::new(s).unwrap();
+    let patterns = patterns.iter().collect::<Vec<_>>();
+
+    for &pattern in patterns.iter().rev() {
+        let inverted = pattern.starts_with('!');
+        let glob = if inverted || pattern.starts_with('\\') {
+            &pattern[1..]
+        } else {
+            pattern
+        };
+
+        let glob = CString::new(glob).unwrap();
+        if unsafe { fnmatch(glob.as_ptr(), s.as_ptr(), 0) } == 0 {
+            return if inverted { None } else { Some(pattern) };
+        }
+    }
+
+    None
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Depend;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_syncdbs_usable_for() {
+        let mut handle = Alpm::new("/", "tests/db/").unwrap();
+        handle.register_syncdb("core", SigLevel::NONE).unwrap();
+        let extra = handle.register_syncdb_mut("extra", SigLevel::NONE).unwrap();
+        extra.set_usage(Usage::SEARCH).unwrap();
+
+        let names = |usage| {
+            handle
+                .syncdbs_usable_for(usage)
+                .iter()
+                .map(|db| db.name().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(Usage::SEARCH), ["core", "extra"]);
+        assert_eq!(names(Usage::INSTALL), ["core"]);
+        assert_eq!(names(Usage::SEARCH | Usage::INSTALL), ["core"]);
+        assert_eq!(names(Usage::NONE), ["core", "extra"]);
+    }
+
+    #[test]
+    fn test_lock_is_stale() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let handle = Alpm::new("/", dir.to_str().unwrap()).unwrap();
+        let lockfile = dir.join("db.lck");
+
+        assert!(!handle.lock_is_stale());
+        let file = fs::File::create(&lockfile).unwrap();
+        assert!(!handle.lock_is_stale());
+        drop(file);
+        // other users' processes can not be checked, so the lock is not known to be stale
+        let root = unsafe { libc::geteuid() } == 0;
+        assert_eq!(handle.lock_is_stale(), root);
+
+        handle.unlock().unwrap();
+        assert!(!lockfile.exists());
+    }
 
     #[test]
     fn test_getters() {
@@ -421,6 +564,43 @@ mod tests {
         assert!(handle.logfile().is_none());
     }
 
+    #[test]
+    fn test_file_siglevels() {
+        let handle = Alpm::new("/", "tests/db/").unwrap();
+
+        handle.set_local_file_siglevel(SigLevel::NONE).unwrap();
+        handle.set_remote_file_siglevel(SigLevel::NONE).unwrap();
+        assert_eq!(handle.local_file_siglevel(), SigLevel::NONE);
+        assert_eq!(handle.remote_file_siglevel(), SigLevel::NONE);
+
+        // USE_DEFAULT is resolved to the default siglevel by the getters
+        handle
+            .set_local_file_siglevel(SigLevel::USE_DEFAULT)
+            .unwrap();
+        handle
+            .set_remote_file_siglevel(SigLevel::USE_DEFAULT)
+            .unwrap();
+        assert_eq!(handle.local_file_siglevel(), handle.default_siglevel());
+        assert_eq!(handle.remote_file_siglevel(), handle.default_siglevel());
+    }
+
+    proptest! {
+        // USE_DEFAULT is left out since the getters resolve it instead of returning it
+        #[test]
+        fn prop_siglevel_round_trip(bits in any::<u32>()) {
+            let level = SigLevel::from_bits_truncate(bits) - SigLevel::USE_DEFAULT;
+            prop_assert_eq!(SigLevel::from_bits(level.bits()), Some(level));
+
+            let handle = Alpm::new("/", "tests/db/").unwrap();
+            if handle.supports_signatures() {
+                handle.set_local_file_siglevel(level).unwrap();
+                handle.set_remote_file_siglevel(level).unwrap();
+                prop_assert_eq!(handle.local_file_siglevel(), level);
+                prop_assert_eq!(handle.remote_file_siglevel(), level);
+            }
+        }
+    }
+
     #[test]
     fn test_setters() {
         let mut handle = Alpm::new("/", "tests/db/").unwrap();
@@ -431,6 +611,42 @@ mod tests {
         handle.add_hookdir("z").unwrap();
         let hooks = handle.hookdirs().iter().collect::<Vec<_>>();
         assert_eq!(hooks, vec!["a/", "b/", "c/", "z/"]);
+        assert!(handle.remove_hookdir("b/").unwrap());
+        assert!(!handle.remove_hookdir("b/").unwrap());
+        let hooks = handle.hookdirs().iter().collect::<Vec<_>>();
+        assert_eq!(hooks, vec!["a/", "c/", "z/"]);
+
+        handle.set_cachedirs(["a", "b"].iter()).unwrap();
+        handle.add_cachedir("c").unwrap();
+        assert!(handle.remove_cachedir("b/").unwrap());
+        assert!(!handle.remove_cachedir("x/").unwrap());
+        let cachedirs = handle.cachedirs().iter().collect::<Vec<_>>();
+        assert_eq!(cachedirs, vec!["a/", "c/"]);
+
+        assert_eq!(handle.parallel_downloads(), 1);
+        handle.set_parallel_downloads(5);
+        assert_eq!(handle.parallel_downloads(), 5);
+
+        handle.set_dbext(".files");
+        assert_eq!(handle.dbext(), ".files");
+
+        handle.set_logfile("/tmp/alpm-test.log").unwrap();
+        assert_eq!(handle.logfile(), Some("/tmp/alpm-test.log"));
+        handle.set_gpgdir("/tmp/alpm-test-gnupg").unwrap();
+        assert_eq!(handle.gpgdir(), Some("/tmp/alpm-test-gnupg/"));
+
+        handle
+            .set_architectures(["x86_64", "x86_64_v3"].iter())
+            .unwrap();
+        handle.add_architecture("any").unwrap();
+        assert!(handle.remove_architecture("x86_64_v3").unwrap());
+        let arches = handle.architectures().iter().collect::<Vec<_>>();
+        assert_eq!(arches, vec!["x86_64", "any"]);
+
+        handle.set_use_syslog(true);
+        assert!(handle.use_syslog());
+        handle.set_use_syslog(false);
+        assert!(!handle.use_syslog());
 
         assert!(!handle.check_space());
         handle.set_check_space(true);
@@ -453,12 +669,61 @@ mod tests {
         let pkgs = handle.ignorepkgs().iter().collect::<Vec<_>>();
         assert_eq!(pkgs.as_slice(), ["a", "b", "c"]);
 
-        /*let indeps = vec!["a", "b", "c"].into_iter().map(|s| Depend::new(s)).collect::<Vec<_>>();
-        let deps = vec!["a", "b", "c"].into_iter().map(|s| Depend::new(s)).collect::<Vec<_>>();
-        handle.set_assume_installed(indeps);
-
-        let ai = handle.assume_installed().collect::<Vec<_>>();
-        assert_eq!(deps.into_iter().map(|d| d.to_string()).collect::<Vec<_>>(), ai.into_iter().map(|d| d.to_string()).collect::<Vec<_>>());
-        */
+        handle
+            .set_ignorepkgs(["linux*", "!linux-lts", "b"].iter())
+            .unwrap();
+        assert_eq!(handle.match_ignore("linux"), Some("linux*"));
+        assert_eq!(handle.match_ignore("linux-headers"), Some("linux*"));
+        assert_eq!(handle.match_ignore("linux-lts"), None);
+        assert_eq!(handle.match_ignore("b"), Some("b"));
+        assert_eq!(handle.match_ignore("c"), None);
+        handle.add_ignoregroup("base*").unwrap();
+        assert_eq!(handle.match_ignoregroup("base-devel"), Some("base*"));
+        assert_eq!(handle.match_ignoregroup("xorg"), None);
+        handle.set_ignoregroups(["xorg", "gnome"].iter()).unwrap();
+        assert!(handle.remove_ignoregroup("xorg").unwrap());
+        assert!(!handle.remove_ignoregroup("xorg").unwrap());
+        let groups = handle.ignoregroups().iter().collect::<Vec<_>>();
+        assert_eq!(groups, vec!["gnome"]);
+        assert!(handle.remove_ignorepkg("b").unwrap());
+        assert_eq!(handle.match_ignore("b"), None);
+
+        handle
+            .set_overwrite_files(["usr/lib/*", "etc/x"].iter())
+            .unwrap();
+        handle.add_overwrite_file("opt/*").unwrap();
+        assert!(handle.remove_overwrite_file("etc/x").unwrap());
+        let overwrite = handle.overwrite_files().iter().collect::<Vec<_>>();
+        assert_eq!(overwrite, vec!["usr/lib/*", "opt/*"]);
+
+        handle.add_noextract("usr/share/doc/*").unwrap();
+        handle.add_noupgrade("etc/hosts").unwrap();
+        assert!(handle.remove_noextract("usr/share/doc/*").unwrap());
+        assert!(handle.remove_noupgrade("etc/hosts").unwrap());
+        assert!(!handle.remove_noupgrade("etc/hosts").unwrap());
+        assert!(handle.noextracts().is_empty());
+
+        let deps = ["a", "b=1", "c>=2"].map(Depend::new);
+        handle.set_assume_installed(deps.iter()).unwrap();
+        handle.add_assume_installed(&Depend::new("d")).unwrap();
+        assert!(handle.remove_assume_installed(&deps[0]).unwrap());
+        let ai = handle
+            .assume_installed()
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(ai, ["b=1", "c>=2", "d"]);
+
+        handle
+            .set_noupgrades(["etc/pacman.conf", "!etc/fstab"].iter())
+            .unwrap();
+        let noupgrades = handle.noupgrades().iter().collect::<Vec<_>>();
+        assert_eq!(noupgrades, vec!["etc/pacman.conf", "!etc/fstab"]);
+        handle.add_ignorepkg("x").unwrap();
+        let handle = std::rc::Rc::new(handle);
+        assert_eq!(handle.match_noupgrade("etc/pacman.conf"), Match::Yes);
+        assert_eq!(handle.match_noupgrade("etc/fstab"), Match::Inverted);
+        assert_eq!(handle.match_noextract("etc/pacman.conf"), Match::No);
+        assert_eq!(handle.match_ignore("x"), Some("x"));
     }
 }
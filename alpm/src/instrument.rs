@@ -0,0 +1,81 @@
+#[cfg(feature = "instrument")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Clone, Copy)]
+pub(crate) enum Kind {
+    Handle,
+    LoadedPackage,
+    Depend,
+    List,
+    Signature,
+    ChangeLog,
+    #[cfg_attr(not(feature = "mtree"), allow(dead_code))]
+    MTree,
+}
+
+#[cfg(feature = "instrument")]
+static LIVE: [AtomicUsize; 7] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+// Called when the crate takes ownership of an object that it will later free.
+#[inline]
+pub(crate) fn track_new(kind: Kind) {
+    #[cfg(feature = "instrument")]
+    LIVE[kind as usize].fetch_add(1, Ordering::Relaxed);
+    #[cfg(not(feature = "instrument"))]
+    let _ = kind;
+}
+
+// Called when an object is freed or ownership of it is passed back to libalpm.
+#[inline]
+pub(crate) fn track_free(kind: Kind) {
+    #[cfg(feature = "instrument")]
+    LIVE[kind as usize].fetch_sub(1, Ordering::Relaxed);
+    #[cfg(not(feature = "instrument"))]
+    let _ = kind;
+}
+
+/// The number of libalpm objects currently owned by the crate, as returned by
+/// [`live_objects`].
+///
+/// Packages, databases and other objects borrowed from a handle are freed by libalpm and are
+/// not counted. Objects stored in an [`AlpmListMut`](crate::AlpmListMut) are counted as long
+/// as the list owns them.
+#[cfg(feature = "instrument")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LiveObjects {
+    pub handles: usize,
+    pub loaded_packages: usize,
+    pub depends: usize,
+    pub lists: usize,
+    pub signatures: usize,
+    pub changelogs: usize,
+    pub mtrees: usize,
+}
+
+/// Counts the libalpm objects currently owned by the crate across all threads.
+///
+/// A count that keeps growing in a long running process points to values that are leaked,
+/// for example with [`std::mem::forget`] or by releasing lists with
+/// [`AlpmListMut::into_ptr`](crate::AlpmListMut::into_ptr) and never freeing them.
+#[cfg(feature = "instrument")]
+pub fn live_objects() -> LiveObjects {
+    let get = |kind: Kind| LIVE[kind as usize].load(Ordering::Relaxed);
+
+    LiveObjects {
+        handles: get(Kind::Handle),
+        loaded_packages: get(Kind::LoadedPackage),
+        depends: get(Kind::Depend),
+        lists: get(Kind::List),
+        signatures: get(Kind::Signature),
+        changelogs: get(Kind::ChangeLog),
+        mtrees: get(Kind::MTree),
+    }
+}
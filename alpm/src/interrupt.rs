@@ -0,0 +1,157 @@
+use crate::{Alpm, Error, Result};
+
+use alpm_sys::*;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::os::raw::c_int;
+use std::sync::{Arc, Mutex};
+
+struct HandlePtr(*mut alpm_handle_t);
+
+// The handle is only used through alpm_trans_interrupt, which is safe to call while another
+// thread is committing, and the mutex keeps the transaction from being freed meanwhile.
+unsafe impl Send for HandlePtr {}
+
+// Whether the handle has a transaction is tracked here, so a failed interrupt can be
+// mapped to an error without reading the errno, which the owning thread may be writing.
+struct State {
+    handle: Option<HandlePtr>,
+    trans: bool,
+}
+
+type Shared = Arc<Mutex<State>>;
+
+thread_local! {
+    static INTERRUPTERS: RefCell<HashMap<usize, Shared>> = RefCell::new(HashMap::new());
+}
+
+fn shared(handle: *mut alpm_handle_t) -> Option<Shared> {
+    INTERRUPTERS
+        .try_with(|i| i.borrow().get(&(handle as usize)).cloned())
+        .ok()
+        .flatten()
+}
+
+/// Runs `f`, which creates the transaction if `init` is set and frees it otherwise, while no
+/// interrupter is using it.
+pub(crate) fn with_trans_lock(
+    handle: *mut alpm_handle_t,
+    init: bool,
+    f: impl FnOnce() -> c_int,
+) -> c_int {
+    match shared(handle) {
+        Some(shared) => {
+            let mut state = shared.lock().unwrap_or_else(|e| e.into_inner());
+            let ret = f();
+            // releasing frees the transaction even when unlocking the database fails
+            if !init || ret == 0 {
+                state.trans = init;
+            }
+            ret
+        }
+        None => f(),
+    }
+}
+
+/// Stops the interrupters of a handle that is about to be released.
+pub(crate) fn remove_interrupters(handle: *mut alpm_handle_t) {
+    let shared = INTERRUPTERS
+        .try_with(|i| i.borrow_mut().remove(&(handle as usize)))
+        .ok()
+        .flatten();
+    if let Some(shared) = shared {
+        shared.lock().unwrap_or_else(|e| e.into_inner()).handle = None;
+    }
+}
+
+/// Interrupts the transaction of a handle from another thread, see
+/// [`Alpm::trans_interrupter`].
+#[derive(Clone)]
+pub struct TransactionInterrupter {
+    handle: Shared,
+}
+
+impl std::fmt::Debug for TransactionInterrupter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransactionInterrupter").finish()
+    }
+}
+
+impl TransactionInterrupter {
+    /// Interrupts the transaction being committed.
+    ///
+    /// libalpm stops at the next safe point and the commit returns [`Error::TransAbort`].
+    /// This fails with [`Error::TransNull`] if the handle has no transaction, with
+    /// [`Error::TransType`] if the transaction is not being committed and with
+    /// [`Error::HandleNull`] if the handle has been released.
+    pub fn interrupt(&self) -> Result<()> {
+        let state = self.handle.lock().unwrap_or_else(|e| e.into_inner());
+        let handle = state.handle.as_ref().ok_or(Error::HandleNull)?.0;
+
+        let ret = unsafe { alpm_trans_interrupt(handle) };
+        match (ret, state.trans) {
+            (0, _) => Ok(()),
+            (_, false) => Err(Error::TransNull),
+            (_, true) => Err(Error::TransType),
+        }
+    }
+}
+
+impl Alpm {
+    /// Creates a token that can interrupt this handle's transactions from another thread,
+    /// such as a Ctrl-C handler or a cancel button.
+    ///
+    /// The token can be cloned and outlive the handle, after which it does nothing.
+    pub fn trans_interrupter(&self) -> TransactionInterrupter {
+        let handle = self.as_ptr();
+        let shared = INTERRUPTERS.with(|i| {
+            i.borrow_mut()
+                .entry(handle as usize)
+                .or_insert_with(|| {
+                    let state = State {
+                        handle: Some(HandlePtr(handle)),
+                        trans: self.trans_flags().is_ok(),
+                    };
+                    Arc::new(Mutex::new(state))
+                })
+                .clone()
+        });
+        TransactionInterrupter { handle: shared }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TransFlag;
+
+    fn assert_send<T: Send + Sync + 'static>(_: &T) {}
+
+    #[test]
+    fn test_trans_interrupter() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+        let interrupter = handle.trans_interrupter();
+        assert_send(&interrupter);
+
+        assert_eq!(interrupter.interrupt(), Err(Error::TransNull));
+        handle.trans_init(TransFlag::NO_LOCK).unwrap();
+        // only a commit can be interrupted
+        let thread = interrupter.clone();
+        let ret = std::thread::spawn(move || thread.interrupt())
+            .join()
+            .unwrap();
+        assert_eq!(ret, Err(Error::TransType));
+        // created while the transaction exists
+        assert_eq!(
+            handle.trans_interrupter().interrupt(),
+            Err(Error::TransType)
+        );
+
+        handle.trans_release().unwrap();
+        assert_eq!(interrupter.interrupt(), Err(Error::TransNull));
+
+        drop(handle);
+        assert_eq!(interrupter.interrupt(), Err(Error::HandleNull));
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{AlpmList, Config, ConfigCallbacks, Db, DbConfig, Dep, Pkg, SigLevel};
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+impl Db {
+    /// Writes the packages in the database to `w` as newline delimited JSON, one object per
+    /// package.
+    ///
+    /// Each package is written as soon as it is formatted so memory use does not grow with
+    /// the size of the database. `w` is written to once per package, so wrap it in a
+    /// [`BufWriter`](std::io::BufWriter) if that is expensive.
+    pub fn export_json<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let mut line = String::new();
+
+        for pkg in self.pkgs() {
+            line.clear();
+            pkg_json(&mut line, self.name(), pkg);
+            line.push('\n');
+            w.write_all(line.as_bytes())?;
+        }
+
+        w.flush()
+    }
+}
+
+impl Config {
+    /// Formats the config as a JSON object.
+    ///
+    /// Siglevels are written as lists of the flags that are set.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        out.push('{');
+        field(&mut out, "root", true);
+        string(&mut out, &self.root);
+        field(&mut out, "dbpath", false);
+        string(&mut out, &self.dbpath);
+        field(&mut out, "lockfile", false);
+        string(&mut out, &self.lockfile);
+        field(&mut out, "gpgdir", false);
+        optional(&mut out, self.gpgdir.as_deref());
+        field(&mut out, "logfile", false);
+        optional(&mut out, self.logfile.as_deref());
+        field(&mut out, "cachedirs", false);
+        owned(&mut out, &self.cachedirs);
+        field(&mut out, "hookdirs", false);
+        owned(&mut out, &self.hookdirs);
+        field(&mut out, "architectures", false);
+        owned(&mut out, &self.architectures);
+        field(&mut out, "noupgrades", false);
+        owned(&mut out, &self.noupgrades);
+        field(&mut out, "noextracts", false);
+        owned(&mut out, &self.noextracts);
+        field(&mut out, "ignorepkgs", false);
+        owned(&mut out, &self.ignorepkgs);
+        field(&mut out, "ignoregroups", false);
+        owned(&mut out, &self.ignoregroups);
+        field(&mut out, "overwrite_files", false);
+        owned(&mut out, &self.overwrite_files);
+        field(&mut out, "assume_installed", false);
+        owned(&mut out, &self.assume_installed);
+        field(&mut out, "use_syslog", false);
+        let _ = write!(out, "{}", self.use_syslog);
+        field(&mut out, "check_space", false);
+        let _ = write!(out, "{}", self.check_space);
+        field(&mut out, "dbext", false);
+        string(&mut out, &self.dbext);
+        field(&mut out, "parallel_downloads", false);
+        let _ = write!(out, "{}", self.parallel_downloads);
+        field(&mut out, "default_siglevel", false);
+        siglevel(&mut out, self.default_siglevel);
+        field(&mut out, "local_file_siglevel", false);
+        siglevel(&mut out, self.local_file_siglevel);
+        field(&mut out, "remote_file_siglevel", false);
+        siglevel(&mut out, self.remote_file_siglevel);
+        field(&mut out, "syncdbs", false);
+        out.push('[');
+        for (i, db) in self.syncdbs.iter().enumerate() {
+            if i != 0 {
+                out.push(',');
+            }
+            db_config_json(&mut out, db);
+        }
+        out.push(']');
+        field(&mut out, "callbacks", false);
+        callbacks_json(&mut out, &self.callbacks);
+        out.push('}');
+        out
+    }
+}
+
+fn db_config_json(out: &mut String, db: &DbConfig) {
+    out.push('{');
+    field(out, "name", true);
+    string(out, &db.name);
+    field(out, "servers", false);
+    owned(out, &db.servers);
+    field(out, "siglevel", false);
+    siglevel(out, db.siglevel);
+    field(out, "usage", false);
+    match db.usage {
+        Some(usage) => strings(out, usage.iter_names().map(|(name, _)| name)),
+        None => out.push_str("null"),
+    }
+    out.push('}');
+}
+
+fn callbacks_json(out: &mut String, cbs: &ConfigCallbacks) {
+    let cbs = [
+        ("log", cbs.log),
+        ("download", cbs.download),
+        ("fetch", cbs.fetch),
+        ("event", cbs.event),
+        ("question", cbs.question),
+        ("progress", cbs.progress),
+    ];
+
+    out.push('{');
+    for (i, (name, set)) in cbs.iter().enumerate() {
+        field(out, name, i == 0);
+        let _ = write!(out, "{}", set);
+    }
+    out.push('}');
+}
+
+fn pkg_json(out: &mut String, repo: &str, pkg: &Pkg) {
+    out.push('{');
+    field(out, "repo", true);
+    string(out, repo);
+    field(out, "name", false);
+    string(out, pkg.name());
+    field(out, "base", false);
+    optional(out, pkg.base());
+    field(out, "version", false);
+    string(out, pkg.version());
+    field(out, "desc", false);
+    optional(out, pkg.desc());
+    field(out, "arch", false);
+    optional(out, pkg.arch());
+    field(out, "url", false);
+    optional(out, pkg.url());
+    field(out, "packager", false);
+    optional(out, pkg.packager());
+    field(out, "filename", false);
+    optional(out, pkg.filename());
+    field(out, "build_date", false);
+    let _ = write!(out, "{}", pkg.build_date());
+    field(out, "size", false);
+    let _ = write!(out, "{}", pkg.size());
+    field(out, "isize", false);
+    let _ = write!(out, "{}", pkg.isize());
+    field(out, "licenses", false);
+    strings(out, pkg.licenses());
+    field(out, "groups", false);
+    strings(out, pkg.groups());
+    field(out, "depends", false);
+    deps(out, pkg.depends());
+    field(out, "optdepends", false);
+    deps(out, pkg.optdepends());
+    field(out, "makedepends", false);
+    deps(out, pkg.makedepends());
+    field(out, "checkdepends", false);
+    deps(out, pkg.checkdepends());
+    field(out, "provides", false);
+    deps(out, pkg.provides());
+    field(out, "conflicts", false);
+    deps(out, pkg.conflicts());
+    field(out, "replaces", false);
+    deps(out, pkg.replaces());
+    out.push('}');
+}
+
+fn field(out: &mut String, key: &str, first: bool) {
+    if !first {
+        out.push(',');
+    }
+    string(out, key);
+    out.push(':');
+}
+
+fn optional(out: &mut String, s: Option<&str>) {
+    match s {
+        Some(s) => string(out, s),
+        None => out.push_str("null"),
+    }
+}
+
+fn strings<'a, I: IntoIterator<Item = &'a str>>(out: &mut String, list: I) {
+    out.push('[');
+    for (i, s) in list.into_iter().enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+        string(out, s);
+    }
+    out.push(']');
+}
+
+fn owned(out: &mut String, list: &[String]) {
+    strings(out, list.iter().map(|s| s.as_str()))
+}
+
+fn siglevel(out: &mut String, siglevel: SigLevel) {
+    strings(out, siglevel.iter_names().map(|(name, _)| name))
+}
+
+fn deps(out: &mut String, list: AlpmList<&Dep>) {
+    out.push('[');
+    for (i, dep) in list.iter().enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+        string(out, &dep.to_string());
+    }
+    out.push(']');
+}
+
+fn string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Alpm, SigLevel};
+
+    #[test]
+    fn test_string() {
+        let mut out = String::new();
+        string(&mut out, "a \"b\"\\\n\u{1}é");
+        assert_eq!(out, r#""a \"b\"\\\n\u0001é""#);
+    }
+
+    #[test]
+    fn test_export_json() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        let db = handle.register_syncdb("core", SigLevel::NONE).unwrap();
+
+        let mut out = Vec::new();
+        db.export_json(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), db.pkgs().len());
+        assert!(lines
+            .iter()
+            .all(|l| l.starts_with(r#"{"repo":"core","name":""#) && l.ends_with('}')));
+
+        let pacman = lines
+            .iter()
+            .find(|l| l.contains(r#""name":"pacman""#))
+            .unwrap();
+        assert!(pacman.contains(r#""groups":["base","base-devel"]"#));
+    }
+}
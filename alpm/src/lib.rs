@@ -10,23 +10,36 @@ mod be_local;
 mod be_pkg;
 mod be_sync;
 mod cb;
+mod config;
 mod conflict;
 mod db;
+mod db_stats;
 mod deps;
 mod dload;
 mod error;
 mod filelist;
 mod handle;
+mod instrument;
+mod interrupt;
+mod json;
 mod list;
 mod list_mut;
 mod list_with;
 mod log;
+mod metrics;
 #[cfg(feature = "mtree")]
 mod mtree;
 mod package;
+mod path;
+mod pool;
+mod question;
 mod remove;
+mod search;
+mod shared;
 mod signing;
 mod sync;
+#[cfg(feature = "tracing")]
+mod trace;
 mod trans;
 mod types;
 mod unions;
@@ -37,18 +50,30 @@ mod version;
 pub use crate::add::*;
 pub use crate::alpm::*;
 pub use crate::be_pkg::*;
+pub use crate::be_sync::SyncDbRepair;
 pub use crate::cb::*;
+pub use crate::config::*;
 pub use crate::conflict::*;
 pub use crate::db::*;
+pub use crate::db_stats::*;
 pub use crate::deps::*;
+pub use crate::dload::*;
 pub use crate::error::*;
 pub use crate::filelist::*;
+#[cfg(feature = "instrument")]
+pub use crate::instrument::*;
+pub use crate::interrupt::TransactionInterrupter;
 pub use crate::list::*;
 pub use crate::list_mut::*;
 pub use crate::list_with::*;
+pub use crate::metrics::Metrics;
 #[cfg(feature = "mtree")]
 pub use crate::mtree::*;
 pub use crate::package::*;
+pub use crate::pool::*;
+pub use crate::question::*;
+pub use crate::search::*;
+pub use crate::shared::*;
 pub use crate::signing::*;
 pub use crate::trans::*;
 pub use crate::types::*;
@@ -1,6 +1,6 @@
 use crate::{
     free, AlpmListMut, Backup, Conflict, Db, DbMut, Dep, DepMissing, Depend, DependMissing,
-    FileConflict, Group, LoadedPackage, OwnedConflict, OwnedFileConflict, Package, Pkg,
+    FileConflict, Group, LoadedPackage, OwnedConflict, OwnedFileConflict, Package, Pkg, XData,
 };
 
 use std::ffi::{c_void, CStr};
@@ -8,6 +8,7 @@ use std::fmt;
 use std::fmt::Debug;
 use std::iter::{ExactSizeIterator, Iterator};
 use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
 use std::os::raw::c_char;
 
 use alpm_sys::*;
@@ -18,6 +19,10 @@ pub unsafe trait IntoAlpmListItem: Sized {
     unsafe fn drop_item(ptr: *mut c_void) {
         Self::into_list_item(ptr);
     }
+    // gives access to an item while leaving it owned by the list
+    unsafe fn with_list_item<R, F: FnOnce(&Self) -> R>(ptr: *mut c_void, f: F) -> R {
+        f(&ManuallyDrop::new(Self::into_list_item(ptr)))
+    }
 }
 
 pub struct AlpmList<'l, T> {
@@ -44,14 +49,17 @@ impl<'a, T: IntoAlpmListItem + Debug> fmt::Debug for AlpmList<'a, T> {
 }
 
 impl<'l, T> AlpmList<'l, T> {
-    pub(crate) unsafe fn from_ptr<'a>(list: *mut alpm_list_t) -> AlpmList<'a, T> {
+    /// # Safety
+    ///
+    /// `list` must be a valid list of `T` that outlives `'a`.
+    pub unsafe fn from_ptr<'a>(list: *mut alpm_list_t) -> AlpmList<'a, T> {
         AlpmList {
             _marker: PhantomData,
             list,
         }
     }
 
-    pub(crate) fn as_ptr(self) -> *mut alpm_list_t {
+    pub fn as_ptr(self) -> *mut alpm_list_t {
         self.list
     }
 
@@ -242,6 +250,12 @@ unsafe impl IntoAlpmListItem for &Backup {
     }
 }
 
+unsafe impl IntoAlpmListItem for &XData {
+    unsafe fn into_list_item(ptr: *mut c_void) -> Self {
+        XData::from_ptr(ptr as _)
+    }
+}
+
 unsafe impl IntoAlpmListItem for OwnedFileConflict {
     unsafe fn into_list_item(ptr: *mut c_void) -> Self {
         OwnedFileConflict::from_ptr(ptr as _)
@@ -258,4 +272,8 @@ unsafe impl IntoAlpmListItem for String {
     unsafe fn drop_item(ptr: *mut c_void) {
         free(ptr)
     }
+    unsafe fn with_list_item<R, F: FnOnce(&Self) -> R>(ptr: *mut c_void, f: F) -> R {
+        let s = CStr::from_ptr(ptr as *mut c_char);
+        f(&s.to_str().unwrap().to_string())
+    }
 }
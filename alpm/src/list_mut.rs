@@ -1,5 +1,6 @@
 use alpm_sys::*;
 
+use crate::instrument::{track_free, track_new, Kind};
 use crate::{
     free, AlpmList, Backup, Conflict, Db, DbMut, Dep, DepMissing, Depend, DependMissing,
     FileConflict, IntoAlpmListItem, Iter, LoadedPackage, OwnedConflict, OwnedFileConflict, Package,
@@ -16,7 +17,18 @@ use std::os::raw::c_char;
 use std::ptr;
 
 extern "C" {
-    fn strndup(cs: *const c_char, n: usize) -> *mut c_char;
+    fn malloc(size: usize) -> *mut c_void;
+}
+
+// like libc's strndup but implemented in terms of malloc so it also works
+// under miri with the mock feature
+unsafe fn strndup(cs: *const c_char, n: usize) -> *mut c_char {
+    let s = malloc(n + 1) as *mut c_char;
+    if !s.is_null() {
+        ptr::copy_nonoverlapping(cs, s, n);
+        *s.add(n) = 0;
+    }
+    s
 }
 
 #[doc(hidden)]
@@ -83,6 +95,7 @@ impl<T: IntoAlpmListItem> Drop for AlpmListMut<T> {
         }
 
         unsafe { alpm_list_free(start) }
+        track_free(Kind::List);
     }
 }
 
@@ -135,7 +148,13 @@ impl<'a, T: IntoAlpmListItem + BorrowAlpmListItem<'a>> AlpmListMut<T> {
 }
 
 impl<T: IntoAlpmListItem> AlpmListMut<T> {
-    pub(crate) unsafe fn from_ptr(list: *mut alpm_list_t) -> AlpmListMut<T> {
+    /// Takes ownership of a raw list. The list and its items will be freed on drop.
+    ///
+    /// # Safety
+    ///
+    /// `list` must be a valid list of `T` that is not owned by anything else.
+    pub unsafe fn from_ptr(list: *mut alpm_list_t) -> AlpmListMut<T> {
+        track_new(Kind::List);
         AlpmListMut {
             list,
             _marker: PhantomData,
@@ -143,12 +162,23 @@ impl<T: IntoAlpmListItem> AlpmListMut<T> {
     }
 
     pub fn new() -> AlpmListMut<T> {
+        track_new(Kind::List);
         AlpmListMut {
             list: ptr::null_mut(),
             _marker: PhantomData,
         }
     }
 
+    pub fn as_ptr(&self) -> *mut alpm_list_t {
+        self.list
+    }
+
+    /// Releases ownership of the list. The caller becomes responsible for freeing it.
+    pub fn into_ptr(self) -> *mut alpm_list_t {
+        track_free(Kind::List);
+        ManuallyDrop::new(self).list
+    }
+
     pub fn len(&self) -> usize {
         unsafe { alpm_list_count(self.list) }
     }
@@ -160,10 +190,10 @@ impl<T: IntoAlpmListItem> AlpmListMut<T> {
         let mut curr = self.list;
 
         while !curr.is_null() {
-            let item = unsafe { ManuallyDrop::new(T::into_list_item((*curr).data)) };
+            let data = unsafe { (*curr).data };
             let next = unsafe { (*curr).next };
-            if !f(&item) {
-                ManuallyDrop::into_inner(item);
+            if !unsafe { T::with_list_item(data, &mut f) } {
+                unsafe { T::drop_item(data) };
                 unsafe { self.list = alpm_list_remove_item(self.list, curr) };
                 unsafe { free(curr as _) };
             }
@@ -209,6 +239,30 @@ impl AlpmListMut<String> {
     }
 }
 
+impl From<AlpmList<'_, &str>> for Vec<String> {
+    fn from(list: AlpmList<'_, &str>) -> Self {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+}
+
+impl From<AlpmListMut<String>> for Vec<String> {
+    fn from(list: AlpmListMut<String>) -> Self {
+        list.into_iter().collect()
+    }
+}
+
+impl<S: AsRef<str>> From<&[S]> for AlpmListMut<String> {
+    fn from(strs: &[S]) -> Self {
+        strs.iter().map(|s| s.as_ref()).collect()
+    }
+}
+
+impl<S: AsRef<str>> From<Vec<S>> for AlpmListMut<String> {
+    fn from(strs: Vec<S>) -> Self {
+        strs.iter().map(|s| s.as_ref()).collect()
+    }
+}
+
 // cant deref so manual impl
 impl<T: IntoAlpmListItem> AlpmListMut<T> {
     pub fn is_empty(&self) -> bool {
@@ -240,6 +294,7 @@ impl<T: IntoAlpmListItem> Drop for IntoIter<T> {
         }
 
         unsafe { alpm_list_free(self.start) }
+        track_free(Kind::List);
     }
 }
 
@@ -659,6 +714,22 @@ mod tests {
         assert_eq!(iter.next(), None);
     }
 
+    #[test]
+    fn test_string_list_from() {
+        let list = AlpmListMut::<String>::from(vec!["a", "b", "c"]);
+        assert_eq!(list.iter().collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert_eq!(Vec::<String>::from(list.list()), ["a", "b", "c"]);
+
+        let list = AlpmListMut::<String>::from(&["d".to_string()][..]);
+        assert_eq!(Vec::<String>::from(list), ["d"]);
+        assert!(Vec::<String>::from(AlpmListMut::<String>::new()).is_empty());
+
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        let pkg = handle.localdb().pkg("pacman").unwrap();
+        let groups: Vec<String> = pkg.groups().into();
+        assert_eq!(groups, ["base", "base-devel"]);
+    }
+
     #[test]
     fn test_depends_list_debug() {
         let handle = Alpm::new("/", "tests/db").unwrap();
@@ -737,6 +808,43 @@ mod tests {
         list.push_str("c");
     }
 
+    #[test]
+    fn test_strings_push_remove() {
+        let mut list = AlpmListMut::new();
+        list.push("a".to_string());
+        list.push_str("b");
+        list.push("c");
+        list.push_str("d");
+        list.push("e");
+        assert_eq!(list.len(), 5);
+
+        assert_eq!(list.remove(1).as_deref(), Some("b"));
+        assert_eq!(list.remove(0).as_deref(), Some("a"));
+        assert_eq!(list.remove(10), None);
+        assert_eq!(list.pop().as_deref(), Some("e"));
+        assert_eq!(list.iter().collect::<Vec<_>>(), ["c", "d"]);
+
+        list.retain(|s| s != "d");
+        assert_eq!(list.first(), Some("c"));
+        assert_eq!(list.last(), Some("c"));
+        list.retain(|_| false);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_strings_into_iter() {
+        let mut list = ["a", "b", "c"].iter().collect::<AlpmListMut<String>>();
+        list.extend(["d", "e"].iter());
+
+        let copy = list.list().to_list_mut();
+        assert_eq!(copy.iter().collect::<Vec<_>>(), ["a", "b", "c", "d", "e"]);
+        drop(copy);
+
+        let mut iter = list.into_iter();
+        assert_eq!(iter.next().as_deref(), Some("a"));
+        assert_eq!(iter.size_hint(), (4, Some(4)));
+    }
+
     #[test]
     fn test_retain() {
         let handle = Alpm::new("/", "tests/db").unwrap();
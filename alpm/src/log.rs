@@ -1,9 +1,15 @@
+#[cfg(feature = "log")]
+use crate::LogLevel;
 use crate::{Alpm, Error};
 use alpm_sys::*;
 
 use std::ffi::CString;
 
 impl Alpm {
+    /// Writes `msg` to the log file and syslog the same way pacman logs transactions, as
+    /// `[prefix] msg`.
+    ///
+    /// `msg` is written as is, so it should end with a newline. See also [`log_action!`](crate::log_action!).
     pub fn log_action<S1: Into<Vec<u8>>, S2: Into<Vec<u8>>>(
         &self,
         prefix: S1,
@@ -12,11 +18,39 @@ impl Alpm {
         let s = CString::new(msg).unwrap();
         let p = CString::new(prefix).unwrap();
 
-        let ret = unsafe { alpm_logaction(self.as_ptr(), p.as_ptr(), s.as_ptr()) };
+        // msg is not a format string
+        let ret = unsafe { alpm_logaction(self.as_ptr(), p.as_ptr(), c"%s".as_ptr(), s.as_ptr()) };
         self.check_ret(ret)
     }
 }
 
+#[cfg(feature = "log")]
+impl Alpm {
+    /// Sets a log callback that forwards libalpm's messages to the [`log`](::log) crate.
+    ///
+    /// Messages are logged with the target `alpm`. Errors and warnings map to the same
+    /// levels, debug messages to debug and function messages to trace.
+    pub fn set_log_cb_to_log_crate(&self) {
+        self.set_log_cb((), |level, msg, _| {
+            let level = log_level(level);
+            ::log::log!(target: "alpm", level, "{}", msg.trim_end());
+        });
+    }
+}
+
+#[cfg(feature = "log")]
+fn log_level(level: LogLevel) -> ::log::Level {
+    if level.intersects(LogLevel::ERROR) {
+        ::log::Level::Error
+    } else if level.intersects(LogLevel::WARNING) {
+        ::log::Level::Warn
+    } else if level.intersects(LogLevel::DEBUG) {
+        ::log::Level::Debug
+    } else {
+        ::log::Level::Trace
+    }
+}
+
 /// Logs a formatted message.
 ///
 /// A wrapper around the [`Alpm::log_action`] function that acts similar to writeln!.
@@ -50,3 +84,37 @@ macro_rules! log_action {
         $handle.log_action($prefix, s)
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_action() {
+        let dir = tempfile::tempdir().unwrap();
+        let logfile = dir.path().join("pacman.log");
+
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+        handle.set_logfile(logfile.to_str().unwrap()).unwrap();
+        handle.log_action("test", "100% %s %d done\n").unwrap();
+        crate::log_action!(handle, "test", "{}%", 50).unwrap();
+        drop(handle);
+
+        let log = std::fs::read_to_string(&logfile).unwrap();
+        let lines = log.lines().collect::<Vec<_>>();
+        assert!(lines[0].ends_with("[test] 100% %s %d done"));
+        assert!(lines[1].ends_with("[test] 50%"));
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_log_level() {
+        assert_eq!(log_level(LogLevel::ERROR), ::log::Level::Error);
+        assert_eq!(log_level(LogLevel::WARNING), ::log::Level::Warn);
+        assert_eq!(log_level(LogLevel::DEBUG), ::log::Level::Debug);
+        assert_eq!(log_level(LogLevel::FUNCTION), ::log::Level::Trace);
+
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        handle.set_log_cb_to_log_crate();
+    }
+}
@@ -0,0 +1,146 @@
+use crate::{Alpm, AnyDownloadEvent, DownloadEvent, DownloadResult, Result};
+
+use alpm_sys::alpm_handle_t;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Hooks for collecting operational metrics from a handle, set with [`Alpm::set_metrics`].
+///
+/// All methods have empty default implementations so only the interesting ones need to be
+/// implemented.
+pub trait Metrics {
+    /// Called after [`update`](crate::AlpmList::update) with the names of the databases
+    /// that were updated.
+    fn db_update(&self, _dbs: &[&str], _duration: Duration, _result: &Result<bool>) {}
+
+    /// Called when a download finishes.
+    ///
+    /// `bytes` is the number of bytes transferred by libalpm, which is 0 if the file was up
+    /// to date or the download failed.
+    fn download(&self, _filename: &str, _bytes: u64, _duration: Duration, _result: DownloadResult) {
+    }
+
+    /// Called after a transaction is committed.
+    fn transaction(&self, _duration: Duration, _result: &Result<()>) {}
+}
+
+pub(crate) struct MetricsState {
+    pub(crate) metrics: Box<dyn Metrics>,
+    downloads: RefCell<HashMap<String, Instant>>,
+}
+
+// Handles are not Send so a handle is only ever used on the thread it was created on,
+// which lets the metrics be found from the raw handle where only that is available.
+thread_local! {
+    static METRICS: RefCell<HashMap<usize, Rc<MetricsState>>> = RefCell::new(HashMap::new());
+}
+
+pub(crate) fn metrics(handle: *mut alpm_handle_t) -> Option<Rc<MetricsState>> {
+    METRICS
+        .try_with(|m| m.borrow().get(&(handle as usize)).cloned())
+        .ok()
+        .flatten()
+}
+
+pub(crate) fn remove_metrics(handle: *mut alpm_handle_t) {
+    let _ = METRICS.try_with(|m| m.borrow_mut().remove(&(handle as usize)));
+}
+
+pub(crate) fn download_event(handle: *mut alpm_handle_t, filename: &str, event: &AnyDownloadEvent) {
+    let Some(state) = metrics(handle) else {
+        return;
+    };
+
+    match event.event() {
+        DownloadEvent::Init(_) => {
+            let mut downloads = state.downloads.borrow_mut();
+            downloads.insert(filename.to_string(), Instant::now());
+        }
+        DownloadEvent::Completed(done) => {
+            let start = state.downloads.borrow_mut().remove(filename);
+            let duration = start.map(|s| s.elapsed()).unwrap_or_default();
+            let bytes = done.total.max(0) as u64;
+            state
+                .metrics
+                .download(filename, bytes, duration, done.result);
+        }
+        DownloadEvent::Progress(_) | DownloadEvent::Retry(_) => (),
+    }
+}
+
+impl Alpm {
+    /// Reports db updates, downloads and transaction commits made with this handle to
+    /// `metrics`, replacing any metrics set before.
+    ///
+    /// Downloads are observed through the download callback, so one that does nothing is set
+    /// if there is none. Replacing the download callback later keeps the metrics working,
+    /// while [`take_raw_dl_cb`](Alpm::take_raw_dl_cb) stops downloads from being reported.
+    pub fn set_metrics<M: Metrics + 'static>(&self, metrics: M) {
+        let state = MetricsState {
+            metrics: Box::new(metrics),
+            downloads: RefCell::new(HashMap::new()),
+        };
+        let handle = self.as_ptr() as usize;
+        METRICS.with(|m| m.borrow_mut().insert(handle, Rc::new(state)));
+
+        if unsafe { (*self.cbs.dl.get()).is_none() } {
+            self.set_dl_cb((), |_, _, _| ());
+        }
+    }
+
+    /// Stops reporting metrics.
+    pub fn unset_metrics(&self) {
+        remove_metrics(self.as_ptr());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, SigLevel};
+    use std::cell::Cell;
+
+    #[derive(Default)]
+    struct Counts {
+        updates: Cell<usize>,
+        transactions: Cell<usize>,
+    }
+
+    impl Metrics for Rc<Counts> {
+        fn db_update(&self, dbs: &[&str], _: Duration, result: &Result<bool>) {
+            assert_eq!(dbs, ["core"]);
+            assert!(result.is_err());
+            self.updates.set(self.updates.get() + 1);
+        }
+
+        fn transaction(&self, _: Duration, result: &Result<()>) {
+            assert_eq!(result, &Err(Error::TransNull));
+            self.transactions.set(self.transactions.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+        handle.register_syncdb_mut("core", SigLevel::NONE).unwrap();
+        let counts = Rc::new(Counts::default());
+        handle.set_metrics(counts.clone());
+
+        // no servers are set so the update fails
+        assert!(handle.syncdbs_mut().update(false).is_err());
+        assert!(handle.trans_commit().is_err());
+        assert_eq!(counts.updates.get(), 1);
+        assert_eq!(counts.transactions.get(), 1);
+
+        handle.unset_metrics();
+        assert!(handle.trans_commit().is_err());
+        assert_eq!(counts.transactions.get(), 1);
+
+        handle.set_metrics(counts.clone());
+        drop(handle);
+        assert_eq!(Rc::strong_count(&counts), 1);
+    }
+}
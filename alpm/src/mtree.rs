@@ -1,3 +1,4 @@
+use crate::instrument::{track_free, track_new, Kind};
 use crate::Pkg;
 use alpm_sys::*;
 
@@ -29,6 +30,7 @@ impl<'h> Handle for MTree<'h> {
 impl<'h> Drop for MTree<'h> {
     fn drop(&mut self) {
         unsafe { alpm_pkg_mtree_close(self.pkg.as_ptr(), self.as_ptr()) };
+        track_free(Kind::MTree);
     }
 }
 
@@ -49,6 +51,7 @@ impl<'h> Iterator for MTree<'h> {
 
 impl<'h> MTree<'h> {
     pub(crate) unsafe fn new<'a>(pkg: &'a Pkg, archive: *mut archive) -> MTree<'a> {
+        track_new(Kind::MTree);
         MTree {
             pkg,
             archive: NonNull::new_unchecked(archive),
@@ -1,13 +1,15 @@
 use crate::utils::*;
 use crate::{
     AlpmList, AlpmListMut, Backup, ChangeLog, Db, Dep, Error, FileList, PackageFrom, PackageReason,
-    PackageValidation, Result, Signature, Ver,
+    PackageValidation, Result, Signature, Ver, XData,
 };
 
 #[cfg(feature = "mtree")]
 use crate::MTree;
 
 use std::cell::UnsafeCell;
+use std::collections::HashSet;
+use std::io::Read;
 use std::mem::transmute;
 use std::ops::Deref;
 use std::os::raw::c_int;
@@ -63,13 +65,19 @@ impl AsRef<Pkg> for Package {
 }
 
 impl Package {
-    pub(crate) unsafe fn from_ptr<'a>(pkg: *mut alpm_pkg_t) -> &'a Package {
+    /// # Safety
+    ///
+    /// `pkg` must be a valid package that outlives the returned reference.
+    pub unsafe fn from_ptr<'a>(pkg: *mut alpm_pkg_t) -> &'a Package {
         &*(pkg as *mut Package)
     }
 }
 
 impl Pkg {
-    pub(crate) unsafe fn from_ptr<'a>(pkg: *mut alpm_pkg_t) -> &'a Pkg {
+    /// # Safety
+    ///
+    /// `pkg` must be a valid package that outlives the returned reference.
+    pub unsafe fn from_ptr<'a>(pkg: *mut alpm_pkg_t) -> &'a Pkg {
         &*(pkg as *mut Pkg)
     }
 
@@ -77,7 +85,7 @@ impl Pkg {
         unsafe { alpm_pkg_get_handle(self.as_ptr()) }
     }
 
-    pub(crate) fn as_ptr(&self) -> *mut alpm_pkg_t {
+    pub fn as_ptr(&self) -> *mut alpm_pkg_t {
         self.pkg.get()
     }
 
@@ -254,6 +262,22 @@ impl Pkg {
         unsafe { AlpmList::from_ptr(list) }
     }
 
+    /// Non standard fields from the package's metadata.
+    pub fn xdata(&self) -> AlpmList<'_, &XData> {
+        let list = unsafe { alpm_pkg_get_xdata(self.as_ptr()) };
+        unsafe { AlpmList::from_ptr(list) }
+    }
+
+    /// The `pkgtype` xdata field, such as `pkg`, `split`, `debug` or `src`.
+    ///
+    /// Packages built before makepkg recorded the type have no `pkgtype`.
+    pub fn pkgtype(&self) -> Option<&str> {
+        self.xdata()
+            .into_iter()
+            .find(|x| x.name() == "pkgtype")
+            .map(|x| x.value())
+    }
+
     pub fn db(&self) -> Option<&Db> {
         let db = unsafe { alpm_pkg_get_db(self.as_ptr()) };
         self.check_null(db).ok()?;
@@ -267,6 +291,30 @@ impl Pkg {
         Ok(changelog)
     }
 
+    /// The changelog entries of this package that are not in the changelog of `old`.
+    ///
+    /// Entries are the blocks starting at each unindented line, as written by makepkg, and
+    /// are returned in the order they appear in this package's changelog. If `old` has no
+    /// changelog every entry is new.
+    ///
+    /// libalpm can only read changelogs of installed packages and package files, so to compare
+    /// against a pending upgrade load the downloaded file with [`Alpm::pkg_load`](crate::Alpm::pkg_load).
+    pub fn changelog_since(&self, old: &Pkg) -> Result<String> {
+        let mut new_log = String::new();
+        self.changelog()?
+            .read_to_string(&mut new_log)
+            .map_err(|_| Error::Retrieve)?;
+
+        let mut old_log = String::new();
+        if let Ok(mut changelog) = old.changelog() {
+            changelog
+                .read_to_string(&mut old_log)
+                .map_err(|_| Error::Retrieve)?;
+        }
+
+        Ok(new_entries(&old_log, &new_log))
+    }
+
     #[cfg(feature = "mtree")]
     pub fn mtree(&self) -> Result<MTree> {
         let archive = unsafe { alpm_pkg_mtree_open(self.as_ptr()) };
@@ -306,11 +354,71 @@ impl Pkg {
     }
 }
 
+impl<'a> AlpmList<'_, &'a Package> {
+    /// The packages sorted by name, then version, then database name.
+    ///
+    /// The order of package lists returned by libalpm is an implementation detail that may
+    /// change between versions. Use this when the output needs to be reproducible.
+    pub fn sorted(&self) -> Vec<&'a Package> {
+        let mut pkgs = self.iter().collect::<Vec<_>>();
+        pkgs.sort_by(|a, b| cmp_pkgs(a, b));
+        pkgs
+    }
+}
+
+impl<'a> AlpmList<'_, &'a Pkg> {
+    /// The packages sorted by name, then version, then database name.
+    ///
+    /// Lists of `&Pkg` come from [`AlpmListMut::list`], such as on search results.
+    pub fn sorted(&self) -> Vec<&'a Pkg> {
+        let mut pkgs = self.iter().collect::<Vec<_>>();
+        pkgs.sort_by(|a, b| cmp_pkgs(a, b));
+        pkgs
+    }
+}
+
+fn changelog_entries(log: &str) -> Vec<&str> {
+    let mut entries = Vec::new();
+    let mut start = 0;
+    let mut pos = 0;
+
+    for line in log.split_inclusive('\n') {
+        if pos != 0 && !line.starts_with(char::is_whitespace) {
+            entries.push(&log[start..pos]);
+            start = pos;
+        }
+        pos += line.len();
+    }
+    if start != pos {
+        entries.push(&log[start..]);
+    }
+
+    entries
+}
+
+fn new_entries(old: &str, new: &str) -> String {
+    let old = changelog_entries(old)
+        .into_iter()
+        .map(|e| e.trim_end())
+        .collect::<HashSet<_>>();
+
+    changelog_entries(new)
+        .into_iter()
+        .filter(|e| !old.contains(e.trim_end()))
+        .collect()
+}
+
+fn cmp_pkgs(a: &Pkg, b: &Pkg) -> std::cmp::Ordering {
+    a.name()
+        .cmp(b.name())
+        .then_with(|| a.version().vercmp(b.version()))
+        .then_with(|| a.db().map(|db| db.name()).cmp(&b.db().map(|db| db.name())))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{Alpm, SigLevel};
-    use std::io::Read;
     use std::mem::size_of;
 
     #[test]
@@ -329,6 +437,46 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_db() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        handle.register_syncdb("core", SigLevel::NONE).unwrap();
+        handle.register_syncdb("extra", SigLevel::NONE).unwrap();
+
+        let pkg = handle.syncdbs().find_satisfier("linux").unwrap();
+        assert_eq!(pkg.db().unwrap().name(), "core");
+        let pkg = handle.syncdbs().find_satisfier("python").unwrap();
+        assert_eq!(pkg.db().unwrap().name(), "extra");
+        let pkg = handle.localdb().pkg("linux").unwrap();
+        assert_eq!(pkg.db().unwrap().name(), "local");
+    }
+
+    #[test]
+    fn test_sorted() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        let db = handle.register_syncdb("core", SigLevel::NONE).unwrap();
+        handle.register_syncdb("extra", SigLevel::NONE).unwrap();
+
+        let pkgs = db.pkgs().sorted();
+        assert_eq!(pkgs.len(), db.pkgs().len());
+        assert!(pkgs.windows(2).all(|w| w[0].name() < w[1].name()));
+
+        let base = db.group("base").unwrap().packages().sorted();
+        assert!(base.windows(2).all(|w| w[0].name() < w[1].name()));
+
+        let found = handle.find_group_pkgs(handle.syncdbs(), "base");
+        let found = found.list().sorted();
+        assert!(found.windows(2).all(|w| w[0].name() <= w[1].name()));
+    }
+
+    #[test]
+    fn test_xdata() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        let pkg = handle.localdb().pkg("linux").unwrap();
+        assert!(pkg.xdata().is_empty());
+        assert_eq!(pkg.pkgtype(), None);
+    }
+
     #[test]
     fn test_files() {
         let handle = Alpm::new("/", "tests/db").unwrap();
@@ -397,6 +545,24 @@ mod tests {
         assert!(s.contains("2010-02-15 Jaroslav Lichtblau <svetlemodry@archlinux.org>"));
     }
 
+    #[test]
+    fn test_changelog_since() {
+        let old = "2018-02-07 a\n\t* 0.9.1-1\n\n2017-06-24 a\n\t* 0.9-1\n";
+        let new = "2018-11-12 a\n\t* 0.10-1\n\n  fix\n\n2018-02-07 a\n\t* 0.9.1-1\n\n2017-06-24 a\n\t* 0.9-1\n";
+        assert_eq!(
+            new_entries(old, new),
+            "2018-11-12 a\n\t* 0.10-1\n\n  fix\n\n"
+        );
+        assert_eq!(new_entries("", old), old);
+        assert_eq!(new_entries(new, old), "");
+
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        let pkg = handle.localdb().pkg("vifm").unwrap();
+        assert_eq!(pkg.changelog_since(pkg).unwrap(), "");
+        let all = pkg.changelog_since(handle.localdb().pkg("pacman").unwrap());
+        assert!(all.unwrap().starts_with("2018-11-12 Jaroslav Lichtblau"));
+    }
+
     #[test]
     fn test_pkg_optimization() {
         assert!(size_of::<&Pkg>() == size_of::<&Option<Pkg>>());
@@ -0,0 +1,133 @@
+use crate::Alpm;
+
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+impl Alpm {
+    /// The path of `file` on the host, where `file` is relative to the root as in a package's
+    /// file list.
+    ///
+    /// Leading and repeated slashes are ignored and `..` can not go above the root, so the
+    /// result is always inside the root. Symlinks are not resolved.
+    pub fn root_path<P: AsRef<Path>>(&self, file: P) -> PathBuf {
+        let mut path = PathBuf::from(self.root());
+        let depth = path.components().count();
+
+        for component in file.as_ref().components() {
+            match component {
+                Component::Normal(c) => path.push(c),
+                Component::ParentDir if path.components().count() > depth => {
+                    path.pop();
+                }
+                _ => (),
+            }
+        }
+
+        path
+    }
+
+    /// The path of `path` relative to the root, in the form used by package file lists, or
+    /// `None` if it is outside of the root.
+    ///
+    /// Relative paths are taken from the current directory. Symlinks in the parent
+    /// directories are resolved but the last component is not, so a symlink is looked up
+    /// itself rather than what it points to, the same way `pacman -Qo` does. The root itself
+    /// becomes an empty path.
+    pub fn root_relative<P: AsRef<Path>>(&self, path: P) -> Option<PathBuf> {
+        let path = path.as_ref();
+        let path = if path.is_relative() {
+            env::current_dir().ok()?.join(path)
+        } else {
+            path.to_path_buf()
+        };
+
+        let path = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => resolve(parent).join(name),
+            _ => resolve(&path),
+        };
+        let root = resolve(Path::new(self.root()));
+
+        path.strip_prefix(root).ok().map(|p| p.to_path_buf())
+    }
+}
+
+// Resolves symlinks in the part of the path that exists and cleans up the rest.
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(path) = fs::canonicalize(path) {
+        return path;
+    }
+
+    let mut clean = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                clean.pop();
+            }
+            Component::CurDir => (),
+            c => clean.push(c),
+        }
+    }
+
+    for base in clean.ancestors() {
+        if let Ok(resolved) = fs::canonicalize(base) {
+            return resolved.join(clean.strip_prefix(base).unwrap());
+        }
+    }
+    clean
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_path() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        assert_eq!(
+            handle.root_path("usr/bin/pacman"),
+            Path::new("/usr/bin/pacman")
+        );
+        assert_eq!(
+            handle.root_path("//usr//bin/./pacman"),
+            Path::new("/usr/bin/pacman")
+        );
+        assert_eq!(handle.root_path("../../etc"), Path::new("/etc"));
+        assert_eq!(handle.root_path(""), Path::new("/"));
+    }
+
+    #[test]
+    fn test_root_relative() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let root = dir.join("root");
+        fs::create_dir_all(root.join("usr/bin")).unwrap();
+        std::os::unix::fs::symlink("usr/bin", root.join("bin")).unwrap();
+        let dbpath = dir.join("db");
+        fs::create_dir_all(&dbpath).unwrap();
+
+        let handle = Alpm::new(root.to_str().unwrap(), dbpath.to_str().unwrap()).unwrap();
+        assert_eq!(
+            handle.root_path("/usr/bin/pacman"),
+            root.join("usr/bin/pacman")
+        );
+
+        let rel = |p: &Path| handle.root_relative(p);
+        assert_eq!(
+            rel(&root.join("usr/bin/pacman")),
+            Some("usr/bin/pacman".into())
+        );
+        assert_eq!(rel(&root.join("bin/pacman")), Some("usr/bin/pacman".into()));
+        assert_eq!(rel(&root.join("bin")), Some("bin".into()));
+        assert_eq!(
+            rel(&root.join("usr//missing/../bin")),
+            Some("usr/bin".into())
+        );
+        assert_eq!(
+            rel(&root.join("usr/missing/file")),
+            Some("usr/missing/file".into())
+        );
+        assert_eq!(rel(&root), Some("".into()));
+        assert_eq!(rel(&dir.join("db")), None);
+    }
+}
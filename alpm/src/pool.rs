@@ -0,0 +1,252 @@
+use crate::{Alpm, Error, Result, SharedAlpm};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::SystemTime;
+
+type Init = dyn Fn() -> Result<Alpm> + Send + Sync;
+type Fingerprint = Vec<(PathBuf, Option<SystemTime>)>;
+
+/// A pool of handles for running read only queries in parallel.
+///
+/// Each handle is a [`SharedAlpm`] with its own thread, so up to `size` queries can run at
+/// once. Handles are checked out with [`HandlePool::get`] and returned when the
+/// [`PooledAlpm`] is dropped.
+///
+/// Handles only load the databases once, so the pool recreates them when the databases
+/// change on disk. This is detected from the modification times of the sync database files
+/// and the local database directory, which are checked on every call to `get`.
+/// [`HandlePool::invalidate`] can be used to force the handles to be recreated.
+#[derive(Debug, Clone)]
+pub struct HandlePool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    init: Arc<Init>,
+    dbpath: PathBuf,
+    state: Mutex<State>,
+    free: Condvar,
+}
+
+struct State {
+    // None is a slot whose handle failed to be recreated
+    handles: Vec<Option<Entry>>,
+    generation: u64,
+    fingerprint: Fingerprint,
+}
+
+struct Entry {
+    handle: SharedAlpm,
+    generation: u64,
+}
+
+impl HandlePool {
+    /// Creates a pool of `size` handles. `f` is called to create each handle and should
+    /// register any databases the queries need.
+    pub fn new<F>(size: usize, f: F) -> Result<HandlePool>
+    where
+        F: Fn() -> Result<Alpm> + Send + Sync + 'static,
+    {
+        if size == 0 {
+            return Err(Error::WrongArgs);
+        }
+
+        let init: Arc<Init> = Arc::new(f);
+        let first = new_entry(&init, 0)?;
+        let dbpath = first.handle.with(|handle| PathBuf::from(handle.dbpath()));
+        let fingerprint = fingerprint(&dbpath);
+
+        let mut handles = vec![Some(first)];
+        for _ in 1..size {
+            handles.push(Some(new_entry(&init, 0)?));
+        }
+
+        let state = State {
+            handles,
+            generation: 0,
+            fingerprint,
+        };
+        let inner = Inner {
+            init,
+            dbpath,
+            state: Mutex::new(state),
+            free: Condvar::new(),
+        };
+        Ok(HandlePool {
+            inner: Arc::new(inner),
+        })
+    }
+
+    /// Checks out a handle, waiting for one to become free if they are all in use.
+    ///
+    /// If the databases have changed since the handle was created it is recreated first.
+    /// An error is only returned if recreating the handle fails.
+    pub fn get(&self) -> Result<PooledAlpm> {
+        let fingerprint = fingerprint(&self.inner.dbpath);
+        let mut state = self.inner.state.lock().unwrap();
+        if state.fingerprint != fingerprint {
+            state.fingerprint = fingerprint;
+            state.generation += 1;
+        }
+
+        let entry = loop {
+            match state.handles.pop() {
+                Some(entry) => break entry,
+                None => state = self.inner.free.wait(state).unwrap(),
+            }
+        };
+        let generation = state.generation;
+        drop(state);
+
+        let entry = match entry {
+            Some(entry) if entry.generation == generation => entry,
+            _ => match new_entry(&self.inner.init, generation) {
+                Ok(entry) => entry,
+                Err(err) => {
+                    self.inner.state.lock().unwrap().handles.push(None);
+                    self.inner.free.notify_one();
+                    return Err(err);
+                }
+            },
+        };
+
+        Ok(PooledAlpm {
+            pool: self.inner.clone(),
+            entry: Some(entry),
+        })
+    }
+
+    /// Recreates every handle the next time it is checked out.
+    pub fn invalidate(&self) {
+        self.inner.state.lock().unwrap().generation += 1;
+    }
+}
+
+/// A handle checked out from a [`HandlePool`]. It is returned to the pool on drop.
+#[derive(Debug)]
+pub struct PooledAlpm {
+    pool: Arc<Inner>,
+    entry: Option<Entry>,
+}
+
+impl PooledAlpm {
+    /// Runs `f` on the handle and returns its result.
+    ///
+    /// See [`SharedAlpm::with`].
+    pub fn with<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&Alpm) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.entry.as_ref().unwrap().handle.with(|handle| f(handle))
+    }
+}
+
+impl Drop for PooledAlpm {
+    fn drop(&mut self) {
+        let entry = self.entry.take();
+        self.pool.state.lock().unwrap().handles.push(entry);
+        self.pool.free.notify_one();
+    }
+}
+
+impl std::fmt::Debug for Inner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Inner")
+            .field("dbpath", &self.dbpath)
+            .finish_non_exhaustive()
+    }
+}
+
+impl std::fmt::Debug for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Entry")
+            .field("generation", &self.generation)
+            .finish_non_exhaustive()
+    }
+}
+
+fn new_entry(init: &Arc<Init>, generation: u64) -> Result<Entry> {
+    let init = init.clone();
+    let handle = SharedAlpm::new(move || init())?;
+    Ok(Entry { handle, generation })
+}
+
+fn fingerprint(dbpath: &Path) -> Fingerprint {
+    let mtime = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+
+    let local = dbpath.join("local");
+    let mut ret = vec![(local.clone(), mtime(&local))];
+
+    if let Ok(dir) = fs::read_dir(dbpath.join("sync")) {
+        let mut sync = dir
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .map(|path| {
+                let mtime = mtime(&path);
+                (path, mtime)
+            })
+            .collect::<Vec<_>>();
+        sync.sort();
+        ret.extend(sync);
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SigLevel;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[test]
+    fn test_handle_pool() {
+        let created = Arc::new(AtomicUsize::new(0));
+        let c = created.clone();
+        let pool = HandlePool::new(2, move || {
+            c.fetch_add(1, Ordering::SeqCst);
+            let handle = Alpm::new("/", "tests/db")?;
+            handle.register_syncdb("core", SigLevel::NONE)?;
+            Ok(handle)
+        })
+        .unwrap();
+        assert_eq!(created.load(Ordering::SeqCst), 2);
+
+        let threads = (0..8)
+            .map(|_| {
+                let pool = pool.clone();
+                thread::spawn(move || {
+                    let handle = pool.get().unwrap();
+                    handle.with(|handle| {
+                        let db = handle.syncdbs().first().unwrap();
+                        db.pkg("linux").unwrap().version().to_string()
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            assert!(!thread.join().unwrap().is_empty());
+        }
+        assert_eq!(created.load(Ordering::SeqCst), 2);
+
+        let a = pool.get().unwrap();
+        let b = pool.get().unwrap();
+        drop((a, b));
+
+        pool.invalidate();
+        let a = pool.get().unwrap();
+        assert_eq!(created.load(Ordering::SeqCst), 3);
+        assert_eq!(a.with(|handle| handle.root().to_string()), "/");
+    }
+
+    #[test]
+    fn test_handle_pool_empty() {
+        let err = HandlePool::new(0, || Alpm::new("/", "tests/db")).unwrap_err();
+        assert_eq!(err, Error::WrongArgs);
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{Alpm, AnyQuestion, Question};
+
+/// Answers the questions libalpm asks during a transaction.
+///
+/// Set with [`Alpm::set_question_handler`]. Closures taking an [`AnyQuestion`] are handlers
+/// too, and [`NonInteractive`], [`DenyAll`] and [`Prompt`] cover the common cases.
+pub trait QuestionHandler {
+    /// Answers `question` by setting its answer.
+    fn answer(&mut self, question: AnyQuestion);
+}
+
+impl<F: FnMut(AnyQuestion)> QuestionHandler for F {
+    fn answer(&mut self, question: AnyQuestion) {
+        self(question)
+    }
+}
+
+/// Gives the default answer to every question, like `pacman --noconfirm`.
+///
+/// Ignored packages are installed, replacements are accepted, corrupted packages are deleted
+/// and keys are imported. Conflicting packages are not removed and packages with
+/// unresolvable dependencies are not skipped, so those transactions fail. The first provider
+/// is used.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NonInteractive;
+
+impl QuestionHandler for NonInteractive {
+    fn answer(&mut self, question: AnyQuestion) {
+        let mut question = question.question();
+        let default = default_answer(&question);
+        set_answer(&mut question, default);
+    }
+}
+
+/// Answers no to every question.
+///
+/// Selecting a provider is not a yes or no question so the first provider is still used.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DenyAll;
+
+impl QuestionHandler for DenyAll {
+    fn answer(&mut self, question: AnyQuestion) {
+        set_answer(&mut question.question(), false);
+    }
+}
+
+/// Asks every question with a prompt function.
+///
+/// The function is given the question as pacman words it and the default answer, and returns
+/// the answer. When selecting a provider each provider is offered in turn until one is
+/// accepted, falling back to the first.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Prompt<F>(pub F);
+
+impl<F: FnMut(&str, bool) -> bool> QuestionHandler for Prompt<F> {
+    fn answer(&mut self, question: AnyQuestion) {
+        let mut question = question.question();
+        let default = default_answer(&question);
+
+        let answer = match &mut question {
+            Question::InstallIgnorepkg(q) => (self.0)(
+                &format!(
+                    "{} is in IgnorePkg/IgnoreGroup. Install anyway?",
+                    q.pkg().name()
+                ),
+                default,
+            ),
+            Question::Replace(q) => (self.0)(
+                &format!(
+                    "Replace {} with {}/{}?",
+                    q.oldpkg().name(),
+                    q.newdb().name(),
+                    q.newpkg().name()
+                ),
+                default,
+            ),
+            Question::Conflict(q) => {
+                let conflict = q.conflict();
+                (self.0)(
+                    &format!(
+                        "{} and {} are in conflict. Remove {}?",
+                        conflict.package1().name(),
+                        conflict.package2().name(),
+                        conflict.package2().name()
+                    ),
+                    default,
+                )
+            }
+            Question::Corrupted(q) => (self.0)(
+                &format!(
+                    "File {} is corrupted ({}).\nDo you want to delete it?",
+                    q.filepath(),
+                    q.reason()
+                ),
+                default,
+            ),
+            Question::RemovePkgs(q) => {
+                let names = q
+                    .packages()
+                    .iter()
+                    .map(|p| p.name())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                (self.0)(
+                    &format!(
+                        "The following packages cannot be upgraded due to unresolvable dependencies:\n    {}\nDo you want to skip the above packages for this upgrade?",
+                        names
+                    ),
+                    default,
+                )
+            }
+            Question::SelectProvider(q) => {
+                let depend = q.depend().to_string();
+                let index = q.providers().iter().position(|p| {
+                    let repo = p.db().map(|db| db.name()).unwrap_or("local");
+                    (self.0)(
+                        &format!("Use {}/{} to provide {}?", repo, p.name(), depend),
+                        true,
+                    )
+                });
+                q.set_index(index.unwrap_or(0) as i32);
+                return;
+            }
+            Question::ImportKey(q) => (self.0)(
+                &format!("Import PGP key {}, \"{}\"?", q.fingerprint(), q.uid()),
+                default,
+            ),
+        };
+
+        set_answer(&mut question, answer);
+    }
+}
+
+impl Alpm {
+    /// Sets the question callback to `handler`.
+    pub fn set_question_handler<H: QuestionHandler + 'static>(&self, handler: H) {
+        self.set_question_cb(handler, |question, handler| handler.answer(question));
+    }
+}
+
+// The answers pacman defaults to.
+fn default_answer(question: &Question) -> bool {
+    match question {
+        Question::InstallIgnorepkg(_)
+        | Question::Replace(_)
+        | Question::Corrupted(_)
+        | Question::SelectProvider(_)
+        | Question::ImportKey(_) => true,
+        Question::Conflict(_) | Question::RemovePkgs(_) => false,
+    }
+}
+
+fn set_answer(question: &mut Question, answer: bool) {
+    match question {
+        Question::InstallIgnorepkg(q) => q.set_install(answer),
+        Question::Replace(q) => q.set_replace(answer),
+        Question::Conflict(q) => q.set_remove(answer),
+        Question::Corrupted(q) => q.set_remove(answer),
+        Question::RemovePkgs(q) => q.set_skip(answer),
+        Question::SelectProvider(q) => q.set_index(0),
+        Question::ImportKey(q) => q.set_import(answer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpm_sys::_alpm_question_type_t::*;
+    use alpm_sys::*;
+    use std::ptr;
+
+    fn import_key() -> alpm_question_t {
+        alpm_question_t {
+            import_key: alpm_question_import_key_t {
+                type_: ALPM_QUESTION_IMPORT_KEY,
+                import: 0,
+                uid: c"Arch <arch@example.com>".as_ptr(),
+                fingerprint: c"ABCD".as_ptr(),
+            },
+        }
+    }
+
+    fn remove_pkgs() -> alpm_question_t {
+        alpm_question_t {
+            remove_pkgs: alpm_question_remove_pkgs_t {
+                type_: ALPM_QUESTION_REMOVE_PKGS,
+                skip: 1,
+                packages: ptr::null_mut(),
+            },
+        }
+    }
+
+    fn ask<H: QuestionHandler>(handler: &mut H, mut question: alpm_question_t) -> alpm_question_t {
+        handler.answer(unsafe { AnyQuestion::new(&mut question) });
+        question
+    }
+
+    #[test]
+    fn test_question_handlers() {
+        unsafe {
+            assert_eq!(ask(&mut NonInteractive, import_key()).import_key.import, 1);
+            assert_eq!(ask(&mut NonInteractive, remove_pkgs()).remove_pkgs.skip, 0);
+            assert_eq!(ask(&mut DenyAll, import_key()).import_key.import, 0);
+            assert_eq!(ask(&mut DenyAll, remove_pkgs()).remove_pkgs.skip, 0);
+
+            let mut asked = Vec::new();
+            let mut prompt = Prompt(|msg: &str, default: bool| {
+                asked.push((msg.to_string(), default));
+                !default
+            });
+            assert_eq!(ask(&mut prompt, import_key()).import_key.import, 0);
+            assert_eq!(ask(&mut prompt, remove_pkgs()).remove_pkgs.skip, 1);
+            assert_eq!(
+                asked[0].0,
+                "Import PGP key ABCD, \"Arch <arch@example.com>\"?"
+            );
+            assert!(asked[0].1);
+            assert!(asked[1].0.ends_with("for this upgrade?"));
+            assert!(!asked[1].1);
+        }
+    }
+}
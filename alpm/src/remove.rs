@@ -1,10 +1,65 @@
-use crate::{Alpm, Package, Result};
+use crate::{Alpm, Error, Package, Result};
 
 use alpm_sys::*;
 
 impl Alpm {
     pub fn trans_remove_pkg(&self, pkg: &Package) -> Result<()> {
+        self.check_not_in_callback()?;
         let ret = unsafe { alpm_remove_pkg(self.as_ptr(), pkg.as_ptr()) };
         self.check_ret(ret)
     }
+
+    /// Stages every installed member of the group `name` for removal.
+    ///
+    /// `exclude` is called for each member and returning true leaves it out, the same way
+    /// pacman lets the user deselect group members. Members that are already being removed
+    /// are skipped. Returns the packages that were added to the transaction.
+    ///
+    /// Returns [`Error::PkgNotFound`] if no installed package is in the group.
+    pub fn trans_remove_group<S, F>(&self, name: S, mut exclude: F) -> Result<Vec<&Package>>
+    where
+        S: Into<Vec<u8>>,
+        F: FnMut(&Package) -> bool,
+    {
+        let group = self.localdb().group(name).map_err(|_| Error::PkgNotFound)?;
+        let mut ret = Vec::new();
+
+        for pkg in group.packages() {
+            if exclude(pkg) || self.trans_remove().iter().any(|p| p.name() == pkg.name()) {
+                continue;
+            }
+            self.trans_remove_pkg(pkg)?;
+            ret.push(pkg);
+        }
+
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Alpm, Error, TransFlag};
+
+    #[test]
+    fn test_trans_remove_group() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+        handle.trans_init(TransFlag::NO_LOCK).unwrap();
+
+        let members = handle.localdb().group("base").unwrap().packages().len();
+        let removed = handle
+            .trans_remove_group("base", |pkg| pkg.name() == "linux")
+            .unwrap();
+        assert_eq!(removed.len(), members - 1);
+        assert!(removed.iter().all(|pkg| pkg.name() != "linux"));
+        assert_eq!(handle.trans_remove().len(), removed.len());
+
+        // already staged members are not added twice
+        let again = handle.trans_remove_group("base", |_| false).unwrap();
+        assert_eq!(again.len(), 1);
+        assert_eq!(again[0].name(), "linux");
+
+        let err = handle.trans_remove_group("doesnotexist", |_| false);
+        assert_eq!(err.unwrap_err(), Error::PkgNotFound);
+        handle.trans_release().unwrap();
+    }
 }
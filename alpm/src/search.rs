@@ -0,0 +1,155 @@
+use crate::{AlpmList, Db, Error, Iter, Package, Result, Usage};
+
+use alpm_sys::*;
+
+use std::ffi::{c_char, CString};
+
+struct Regex {
+    target: CString,
+    inner: Box<libc::regex_t>,
+}
+
+impl Regex {
+    fn new(target: &str) -> Result<Regex> {
+        let target = CString::new(target).map_err(|_| Error::InvalidRegex)?;
+        let mut inner = Box::new(unsafe { std::mem::zeroed::<libc::regex_t>() });
+        // the same flags as _alpm_db_search
+        let flags = libc::REG_EXTENDED | libc::REG_NOSUB | libc::REG_ICASE | libc::REG_NEWLINE;
+        let ret = unsafe { libc::regcomp(&mut *inner, target.as_ptr(), flags) };
+        if ret != 0 {
+            return Err(Error::InvalidRegex);
+        }
+        Ok(Regex { target, inner })
+    }
+
+    fn is_match(&self, s: *const c_char) -> bool {
+        !s.is_null() && unsafe { libc::regexec(&*self.inner, s, 0, std::ptr::null_mut(), 0) == 0 }
+    }
+
+    fn matches(&self, pkg: &Package) -> bool {
+        let name = unsafe { alpm_pkg_get_name(pkg.as_ptr()) };
+        if pkg.name().as_bytes() == self.target.as_bytes() || self.is_match(name) {
+            return true;
+        }
+
+        let desc = unsafe { alpm_pkg_get_desc(pkg.as_ptr()) };
+        self.is_match(desc)
+            || pkg
+                .provides()
+                .iter()
+                .any(|dep| self.is_match(unsafe { (*dep.as_ptr()).name }))
+    }
+}
+
+impl Drop for Regex {
+    fn drop(&mut self) {
+        unsafe { libc::regfree(&mut *self.inner) }
+    }
+}
+
+/// An iterator over the packages that match a search, see [`Db::search_iter`].
+pub struct SearchIter<'a> {
+    pkgs: Iter<'a, &'a Package>,
+    regexes: Vec<Regex>,
+}
+
+impl<'a> std::fmt::Debug for SearchIter<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let regexes = self
+            .regexes
+            .iter()
+            .map(|r| r.target.to_string_lossy())
+            .collect::<Vec<_>>();
+        f.debug_struct("SearchIter")
+            .field("regexes", &regexes)
+            .finish()
+    }
+}
+
+impl<'a> Iterator for SearchIter<'a> {
+    type Item = &'a Package;
+
+    fn next(&mut self) -> Option<&'a Package> {
+        let regexes = &self.regexes;
+        self.pkgs
+            .by_ref()
+            .find(|pkg| regexes.iter().all(|r| r.matches(pkg)))
+    }
+}
+
+impl Db {
+    /// Like [`Db::search`] but finds the matches as the iterator is advanced.
+    ///
+    /// The regexes are compiled up front, so an invalid one is reported here rather than by
+    /// the iterator. Packages are matched in the same way and order as [`Db::search`], which
+    /// lets interactive searches show the first results before the whole database has been
+    /// searched. Like [`Db::search`], nothing is found if the database is not used for
+    /// [`Usage::SEARCH`].
+    pub fn search_iter<I, S>(&self, list: I) -> Result<SearchIter<'_>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        if !self.usage()?.contains(Usage::SEARCH) {
+            return Ok(SearchIter {
+                pkgs: unsafe { AlpmList::from_ptr(std::ptr::null_mut()) }.into_iter(),
+                regexes: Vec::new(),
+            });
+        }
+
+        let regexes = list
+            .into_iter()
+            .map(|s| Regex::new(s.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(SearchIter {
+            pkgs: self.pkgs().into_iter(),
+            regexes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Alpm, Error, SigLevel, Usage};
+
+    #[test]
+    fn test_search_iter() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        let db = handle.register_syncdb("core", SigLevel::NONE).unwrap();
+
+        for query in [
+            &["pacman"][..],
+            &["^linux"],
+            &["LINUX", "headers"],
+            &["sh"],
+            &["^mkinitcpio-nfs-utils$"],
+            &[],
+        ] {
+            let expected = db.search(query.iter().cloned()).unwrap();
+            let expected = expected.iter().map(|p| p.name()).collect::<Vec<_>>();
+            let found = db
+                .search_iter(query)
+                .unwrap()
+                .map(|p| p.name())
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected, "{:?}", query);
+        }
+
+        let mut iter = db.search_iter(["linux"]).unwrap();
+        assert!(iter.next().unwrap().name().contains("linux"));
+
+        assert_eq!(db.search_iter(["("]).unwrap_err(), Error::InvalidRegex);
+    }
+
+    #[test]
+    fn test_search_iter_usage() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+        let db = handle.register_syncdb_mut("core", SigLevel::NONE).unwrap();
+        db.set_usage(Usage::SYNC | Usage::INSTALL).unwrap();
+
+        assert!(db.search(["linux"].iter()).unwrap().is_empty());
+        assert_eq!(db.search_iter(["linux"]).unwrap().count(), 0);
+        assert_eq!(db.search_iter(["("]).unwrap().count(), 0);
+    }
+}
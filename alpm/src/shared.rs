@@ -0,0 +1,184 @@
+use crate::{Alpm, Result};
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{channel, sync_channel, Sender};
+use std::thread;
+
+type Job = Box<dyn FnOnce(&mut Alpm) + Send>;
+
+/// A handle that can be shared between threads.
+///
+/// [`Alpm`] is neither [`Send`] nor [`Sync`]: callbacks are not required to be `Send` and
+/// libalpm itself is not thread safe. `SharedAlpm` instead keeps the handle on a dedicated
+/// thread and runs operations on it one at a time. Cloning a `SharedAlpm` is cheap and
+/// every clone refers to the same handle. The handle is released once all clones are dropped.
+#[derive(Debug, Clone)]
+pub struct SharedAlpm {
+    jobs: Sender<Job>,
+}
+
+impl SharedAlpm {
+    /// Creates a new shared handle. `f` is run on the handle's thread to create the underlying
+    /// [`Alpm`], so callbacks set up inside of it do not need to be `Send`.
+    pub fn new<F>(f: F) -> Result<SharedAlpm>
+    where
+        F: FnOnce() -> Result<Alpm> + Send + 'static,
+    {
+        let (jobs, rx) = channel::<Job>();
+        let (init_tx, init_rx) = sync_channel(1);
+
+        thread::Builder::new()
+            .name("alpm".into())
+            .spawn(move || {
+                let mut handle = match f() {
+                    Ok(handle) => {
+                        let _ = init_tx.send(Ok(()));
+                        handle
+                    }
+                    Err(err) => {
+                        let _ = init_tx.send(Err(err));
+                        return;
+                    }
+                };
+
+                for job in rx {
+                    job(&mut handle);
+                }
+            })
+            .expect("failed to spawn alpm thread");
+
+        init_rx
+            .recv()
+            .expect("alpm thread panicked during initialization")?;
+        Ok(SharedAlpm { jobs })
+    }
+
+    /// Runs `f` with exclusive access to the handle and returns its result.
+    ///
+    /// If `f` panics the panic is propagated to the caller and the handle remains usable.
+    pub fn with<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Alpm) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = sync_channel::<thread::Result<R>>(1);
+        let job: Job = Box::new(move |handle| {
+            let ret = panic::catch_unwind(AssertUnwindSafe(|| f(handle)));
+            let _ = tx.send(ret);
+        });
+
+        self.jobs.send(job).expect("alpm thread has exited");
+        match rx.recv().expect("alpm thread has exited") {
+            Ok(ret) => ret,
+            Err(err) => panic::resume_unwind(err),
+        }
+    }
+}
+
+// SharedAlpm, the db stats, the metrics and the interrupters all rely on the handle and the
+// types borrowed from it staying neither Send nor Sync. Dbs and packages are only handed out
+// by reference, so them not being Sync is what keeps them on the handle's thread.
+/// ```compile_fail,E0277
+/// fn assert_send<T: Send>() {}
+/// assert_send::<alpm::Alpm>();
+/// ```
+///
+/// ```compile_fail,E0277
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<alpm::Alpm>();
+/// ```
+///
+/// ```compile_fail,E0277
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<alpm::Db>();
+/// ```
+///
+/// ```compile_fail,E0277
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<alpm::Package>();
+/// ```
+///
+/// ```compile_fail,E0277
+/// fn assert_send<T: Send>() {}
+/// assert_send::<alpm::DbMut<'static>>();
+/// ```
+///
+/// ```compile_fail,E0277
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<alpm::DbMut<'static>>();
+/// ```
+///
+/// ```compile_fail,E0277
+/// fn assert_send<T: Send>() {}
+/// assert_send::<alpm::Transaction<'static>>();
+/// ```
+///
+/// ```compile_fail,E0277
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<alpm::Transaction<'static>>();
+/// ```
+#[cfg(doctest)]
+struct NotSendSync;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AlpmList, AlpmListMut, Backup, Conflict, Dep, DepMissing, Depend, File, FileConflict,
+        SigLevel, SigList, Signature,
+    };
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_send_sync() {
+        assert_send_sync::<SharedAlpm>();
+        assert_send_sync::<Dep>();
+        assert_send_sync::<Depend>();
+        assert_send_sync::<DepMissing>();
+        assert_send_sync::<Conflict>();
+        assert_send_sync::<FileConflict>();
+        assert_send_sync::<Backup>();
+        assert_send_sync::<File>();
+        assert_send_sync::<Signature>();
+        assert_send_sync::<SigList>();
+        // sound because every method adding to a list takes &mut self
+        assert_send_sync::<AlpmList<&str>>();
+        assert_send_sync::<AlpmListMut<String>>();
+    }
+
+    #[test]
+    fn test_shared_alpm() {
+        let handle = SharedAlpm::new(|| {
+            let handle = Alpm::new("/", "tests/db")?;
+            handle.register_syncdb("core", SigLevel::NONE)?;
+            Ok(handle)
+        })
+        .unwrap();
+
+        let threads = (0..4)
+            .map(|_| {
+                let handle = handle.clone();
+                thread::spawn(move || {
+                    handle.with(|handle| {
+                        let db = handle.syncdbs().first().unwrap();
+                        db.pkg("linux").unwrap().version().to_string()
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            assert!(!thread.join().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_shared_alpm_panic() {
+        let handle = SharedAlpm::new(|| Alpm::new("/", "tests/db")).unwrap();
+        let h = handle.clone();
+        let ret = thread::spawn(move || h.with(|_| panic!("oops"))).join();
+        assert!(ret.is_err());
+        assert_eq!(handle.with(|handle| handle.root().to_string()), "/");
+    }
+}
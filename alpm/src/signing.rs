@@ -1,3 +1,4 @@
+use crate::alpm::check_signatures;
 use crate::{free, Alpm, AlpmListMut, Db, Result};
 use crate::{utils::*, Pkg};
 
@@ -151,6 +152,9 @@ pub struct SigList {
     inner: alpm_siglist_t,
 }
 
+unsafe impl Send for SigList {}
+unsafe impl Sync for SigList {}
+
 impl fmt::Debug for SigList {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_list().entries(self.results()).finish()
@@ -192,6 +196,7 @@ impl SigList {
 
 impl Pkg {
     pub fn check_signature(&self, siglist: &mut SigList) -> Result<()> {
+        check_signatures()?;
         let ret = unsafe { alpm_pkg_check_pgp_signature(self.as_ptr(), &mut siglist.inner) };
         match ret {
             0 => Ok(()),
@@ -202,6 +207,7 @@ impl Pkg {
 
 impl Db {
     pub fn check_signature(&self, siglist: &mut SigList) -> Result<()> {
+        check_signatures()?;
         let ret = unsafe { alpm_db_check_pgp_signature(self.as_ptr(), &mut siglist.inner) };
         match ret {
             0 => Ok(()),
@@ -216,6 +222,7 @@ impl Alpm {
         ident: S,
         sig: &[u8],
     ) -> Result<AlpmListMut<String>> {
+        check_signatures()?;
         let ident = CString::new(ident).unwrap();
         let mut keys = ptr::null_mut();
 
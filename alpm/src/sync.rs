@@ -36,8 +36,37 @@ impl Alpm {
 }
 
 impl Alpm {
+    /// Adds every available upgrade to the transaction, like `pacman -Su`.
+    ///
+    /// The upgrades and any packages they replace can be inspected with
+    /// [`trans_add`](Alpm::trans_add) and [`trans_remove`](Alpm::trans_remove) before the
+    /// transaction is prepared.
     pub fn sync_sysupgrade(&self, enable_downgrade: bool) -> Result<()> {
+        self.check_not_in_callback()?;
         let ret = unsafe { alpm_sync_sysupgrade(self.as_ptr(), enable_downgrade as _) };
         self.check_ret(ret)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SigLevel, TransFlag};
+
+    #[test]
+    fn test_sync_sysupgrade() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        handle.register_syncdb("core", SigLevel::NONE).unwrap();
+        handle.register_syncdb("extra", SigLevel::NONE).unwrap();
+
+        handle.trans_init(TransFlag::NO_LOCK).unwrap();
+        handle.sync_sysupgrade(false).unwrap();
+
+        for pkg in handle.trans_add() {
+            assert!(pkg.db().is_some_and(|db| db.name() != "local"));
+            if let Ok(local) = handle.localdb().pkg(pkg.name()) {
+                assert!(pkg.version() > local.version());
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{Alpm, DownloadEvent};
+
+use ::tracing::{debug, info, trace, warn};
+
+impl Alpm {
+    /// Sets event, progress and download callbacks that emit [`tracing`](::tracing) events.
+    ///
+    /// Events are emitted with the target `alpm` inside the spans entered for database
+    /// updates and transactions. Download completions are logged at info, retries at warn,
+    /// other events at debug and progress updates at trace. This replaces any event, progress
+    /// and download callbacks already set.
+    pub fn set_tracing_cbs(&self) {
+        self.set_event_cb((), |event, _| {
+            debug!(target: "alpm", event = ?event.event(), "event");
+        });
+
+        self.set_progress_cb((), |progress, pkgname, percent, howmany, current, _| {
+            trace!(
+                target: "alpm",
+                ?progress,
+                pkgname,
+                percent,
+                howmany,
+                current,
+                "progress"
+            );
+        });
+
+        self.set_dl_cb((), |filename, event, _| match event.event() {
+            DownloadEvent::Init(init) => {
+                debug!(target: "alpm", filename, optional = init.optional, "download started")
+            }
+            DownloadEvent::Progress(progress) => trace!(
+                target: "alpm",
+                filename,
+                downloaded = progress.downloaded,
+                total = progress.total,
+                "download progress"
+            ),
+            DownloadEvent::Retry(retry) => {
+                warn!(target: "alpm", filename, resume = retry.resume, "download retrying")
+            }
+            DownloadEvent::Completed(completed) => info!(
+                target: "alpm",
+                filename,
+                total = completed.total,
+                result = ?completed.result,
+                "download completed"
+            ),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Alpm;
+    use alpm_sys::*;
+
+    #[test]
+    fn test_set_tracing_cbs() {
+        let handle = Alpm::new("/", "tests/db").unwrap();
+        handle.set_tracing_cbs();
+
+        unsafe {
+            assert!(alpm_option_get_eventcb(handle.as_ptr()).is_some());
+            assert!(alpm_option_get_progresscb(handle.as_ptr()).is_some());
+            assert!(alpm_option_get_dlcb(handle.as_ptr()).is_some());
+        }
+    }
+}
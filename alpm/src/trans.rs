@@ -1,13 +1,25 @@
-use crate::{Alpm, AlpmList, AlpmListMut, DependMissing, Error, OwnedConflict, Package, Result};
+use crate::{
+    AddError, Alpm, AlpmList, AlpmListMut, AnyDownloadEvent, AnyEvent, DependMissing,
+    DownloadEvent, DownloadRate, DownloadResult, Error, Event, IntoPkgAdd, OwnedConflict,
+    OwnedFileConflict, Package, PackageOperation, Progress, Result, TransactionInterrupter,
+};
+
+use crate::dload::{finish_fetch_timeout, start_fetch_timeout};
+use crate::interrupt::with_trans_lock;
+use crate::metrics::metrics;
 
 use alpm_sys::_alpm_transflag_t::*;
 use alpm_sys::*;
 
+use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::error::Error as StdError;
 use std::fmt::{Debug, Display};
-use std::hint::unreachable_unchecked;
-use std::marker::PhantomData;
+use std::mem::transmute;
+use std::ops::Deref;
 use std::ptr;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use bitflags::bitflags;
 
@@ -34,81 +46,142 @@ bitflags! {
     }
 }
 
+/// The data returned with a [`PrepareError`].
 #[derive(Debug)]
-pub enum PrepareData<'a> {
-    PkgInvalidArch(AlpmListMut<&'a Package>),
+pub enum PrepareData {
+    /// The packages, as `name-version-arch`, that are not for the configured architectures.
+    PkgInvalidArch(AlpmListMut<String>),
     UnsatisfiedDeps(AlpmListMut<DependMissing>),
     ConflictingDeps(AlpmListMut<OwnedConflict>),
 }
 
-pub struct PrepareError<'a> {
+#[derive(Debug)]
+pub struct PrepareError {
     error: Error,
-    data: *mut alpm_list_t,
-    _marker: PhantomData<&'a ()>,
+    data: Option<PrepareData>,
 }
 
-impl<'a> Debug for PrepareError<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("PrepareError")
-            .field("error", &self.error())
-            .field("data", &self.data())
-            .finish()
-    }
-}
-
-impl<'a> Display for PrepareError<'a> {
+impl Display for PrepareError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         Display::fmt(&self.error, f)
     }
 }
 
-impl<'a> From<PrepareError<'a>> for Error {
-    fn from(err: PrepareError<'a>) -> Error {
+impl From<PrepareError> for Error {
+    fn from(err: PrepareError) -> Error {
         err.error
     }
 }
 
-impl<'a> StdError for PrepareError<'a> {}
+impl StdError for PrepareError {}
+
+impl PrepareError {
+    unsafe fn new(error: Error, data: *mut alpm_list_t) -> PrepareError {
+        let data = match error {
+            Error::PkgInvalidArch => Some(PrepareData::PkgInvalidArch(AlpmListMut::from_ptr(data))),
+            Error::UnsatisfiedDeps => {
+                Some(PrepareData::UnsatisfiedDeps(AlpmListMut::from_ptr(data)))
+            }
+            Error::ConflictingDeps => {
+                Some(PrepareData::ConflictingDeps(AlpmListMut::from_ptr(data)))
+            }
+            _ => {
+                alpm_list_free(data);
+                None
+            }
+        };
+        PrepareError { error, data }
+    }
 
-impl<'a> PrepareError<'a> {
     pub fn error(&self) -> Error {
         self.error
     }
 
-    pub fn data(&self) -> PrepareData {
-        match self.error {
-            Error::PkgInvalidArch => unsafe {
-                PrepareData::PkgInvalidArch(AlpmListMut::from_ptr(self.data))
-            },
-            Error::UnsatisfiedDeps => unsafe {
-                PrepareData::UnsatisfiedDeps(AlpmListMut::from_ptr(self.data))
-            },
-            Error::ConflictingDeps => unsafe {
-                PrepareData::ConflictingDeps(AlpmListMut::from_ptr(self.data))
-            },
-            _ => unsafe { unreachable_unchecked() },
+    /// The packages, dependencies or conflicts that caused the error, for the errors that
+    /// have them.
+    pub fn data(&self) -> Option<&PrepareData> {
+        self.data.as_ref()
+    }
+
+    pub fn into_data(self) -> Option<PrepareData> {
+        self.data
+    }
+}
+
+#[derive(Debug)]
+pub enum CommitProgress<'a> {
+    Event(AnyEvent<'a>),
+    Progress {
+        progress: Progress,
+        pkgname: &'a str,
+        percent: i32,
+        howmany: usize,
+        current: usize,
+    },
+    Download {
+        filename: &'a str,
+        event: AnyDownloadEvent<'a>,
+        /// The smoothed combined speed of all downloads in bytes per second.
+        rate: f64,
+    },
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CommitSummary {
+    pub installed: Vec<String>,
+    pub upgraded: Vec<String>,
+    pub downgraded: Vec<String>,
+    pub reinstalled: Vec<String>,
+    pub removed: Vec<String>,
+    /// Total size of the files successfully downloaded, in bytes.
+    pub downloaded: u64,
+    pub duration: Duration,
+}
+
+struct CommitState {
+    f: *mut dyn FnMut(CommitProgress),
+    summary: CommitSummary,
+    rate: DownloadRate,
+}
+
+impl CommitState {
+    fn record_event(&mut self, event: &AnyEvent) {
+        let Event::PackageOperationDone(op) = event.event() else {
+            return;
+        };
+
+        let summary = &mut self.summary;
+        match op.operation() {
+            PackageOperation::Install(pkg) => summary.installed.push(pkg.name().to_string()),
+            PackageOperation::Upgrade(pkg, _) => summary.upgraded.push(pkg.name().to_string()),
+            PackageOperation::Downgrade(pkg, _) => summary.downgraded.push(pkg.name().to_string()),
+            PackageOperation::Reinstall(pkg, _) => summary.reinstalled.push(pkg.name().to_string()),
+            PackageOperation::Remove(pkg) => summary.removed.push(pkg.name().to_string()),
+        }
+    }
+
+    fn record_download(&mut self, filename: &str, event: &AnyDownloadEvent) {
+        self.rate.update(filename, event);
+        if let DownloadEvent::Completed(event) = event.event() {
+            if event.result == DownloadResult::Success && event.total > 0 {
+                self.summary.downloaded += event.total as u64;
+            }
         }
     }
 }
 
+/// The data returned with a [`CommitError`].
 #[derive(Debug)]
 pub enum CommitData {
-    FileConflict(AlpmListMut<OwnedConflict>),
+    FileConflict(AlpmListMut<OwnedFileConflict>),
+    /// The paths of the package files that failed validation.
     PkgInvalid(AlpmListMut<String>),
 }
 
+#[derive(Debug)]
 pub struct CommitError {
     error: Error,
-    data: *mut alpm_list_t,
-}
-
-impl Debug for CommitError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("CommitError")
-            .field("error", &self.error())
-            .field("data", &self.data())
-            .finish()
-    }
+    data: Option<CommitData>,
 }
 
 impl Display for CommitError {
@@ -126,61 +199,159 @@ impl From<CommitError> for Error {
 impl StdError for CommitError {}
 
 impl CommitError {
+    unsafe fn new(error: Error, data: *mut alpm_list_t) -> CommitError {
+        let data = match error {
+            Error::FileConflicts => Some(CommitData::FileConflict(AlpmListMut::from_ptr(data))),
+            Error::PkgInvalid | Error::PkgInvalidSig | Error::PkgInvalidChecksum => {
+                Some(CommitData::PkgInvalid(AlpmListMut::from_ptr(data)))
+            }
+            _ => {
+                alpm_list_free(data);
+                None
+            }
+        };
+        CommitError { error, data }
+    }
+
     pub fn error(&self) -> Error {
         self.error
     }
 
-    pub fn data(&self) -> CommitData {
-        match self.error {
-            Error::FileConflicts => unsafe {
-                CommitData::FileConflict(AlpmListMut::from_ptr(self.data))
-            },
-            Error::PkgInvalid | Error::PkgInvalidSig | Error::PkgInvalidChecksum => unsafe {
-                CommitData::PkgInvalid(AlpmListMut::from_ptr(self.data))
-            },
-            _ => unsafe { unreachable_unchecked() },
-        }
+    /// The file conflicts or invalid packages that caused the error, for the errors that
+    /// have them.
+    pub fn data(&self) -> Option<&CommitData> {
+        self.data.as_ref()
+    }
+
+    pub fn into_data(self) -> Option<CommitData> {
+        self.data
     }
 }
 
 impl Alpm {
-    pub fn trans_flags(self) -> TransFlag {
+    /// The flags of the current transaction, or [`Error::TransNull`] if there is none.
+    pub fn trans_flags(&self) -> Result<TransFlag> {
         let flags = unsafe { alpm_trans_get_flags(self.as_ptr()) };
-        TransFlag::from_bits(flags as u32).unwrap()
+        if flags == -1 {
+            return Err(self.last_error());
+        }
+        Ok(TransFlag::from_bits_retain(flags as u32))
     }
 
     pub fn trans_prepare(&mut self) -> std::result::Result<(), PrepareError> {
+        #[cfg(feature = "tracing")]
+        let _span = ::tracing::info_span!(target: "alpm", "trans_prepare").entered();
         let mut list = ptr::null_mut();
         let ret = unsafe { alpm_trans_prepare(self.as_ptr(), &mut list) };
         let err = self.check_ret(ret);
 
-        if let Err(err) = err {
-            Err(PrepareError {
-                error: err,
-                data: list,
-                _marker: PhantomData,
-            })
-        } else {
-            Ok(())
+        match err {
+            Err(err) => Err(unsafe { PrepareError::new(err, list) }),
+            Ok(()) => Ok(()),
         }
     }
 
     pub fn trans_commit(&mut self) -> std::result::Result<(), CommitError> {
+        #[cfg(feature = "tracing")]
+        let _span = ::tracing::info_span!(target: "alpm", "trans_commit").entered();
         let mut list = ptr::null_mut();
+        let start = Instant::now();
+        start_fetch_timeout(self.as_ptr());
         let ret = unsafe { alpm_trans_commit(self.as_ptr(), &mut list) };
-        let err = self.check_ret(ret);
-
-        if let Err(err) = err {
-            Err(CommitError {
-                error: err,
-                data: list,
-            })
+        let mut err = self.check_ret(ret);
+        // errors with data are not from downloading and the data depends on the error
+        if list.is_null() {
+            err = finish_fetch_timeout(self.as_ptr(), err);
         } else {
-            Ok(())
+            let _ = finish_fetch_timeout(self.as_ptr(), Ok(()));
+        }
+
+        if let Some(state) = metrics(self.as_ptr()) {
+            state.metrics.transaction(start.elapsed(), &err);
         }
+
+        match err {
+            Err(err) => Err(unsafe { CommitError::new(err, list) }),
+            Ok(()) => Ok(()),
+        }
+    }
+
+    /// Commits the transaction, passing every event, progress update and download event to
+    /// `f` for the duration of the commit.
+    ///
+    /// Any event, progress and download callbacks already set are restored afterwards.
+    pub fn trans_commit_with_progress<F: FnMut(CommitProgress)>(
+        &mut self,
+        mut f: F,
+    ) -> std::result::Result<CommitSummary, CommitError> {
+        let start = Instant::now();
+        let f: &mut dyn FnMut(CommitProgress) = &mut f;
+        // the callbacks are removed before returning so f does not actually need to be 'static
+        let f = unsafe {
+            transmute::<&mut dyn FnMut(CommitProgress), *mut (dyn FnMut(CommitProgress) + 'static)>(
+                f,
+            )
+        };
+        let state = Rc::new(RefCell::new(CommitState {
+            f,
+            summary: CommitSummary::default(),
+            rate: DownloadRate::default(),
+        }));
+
+        let event_cb = self.take_raw_event_cb();
+        let progress_cb = self.take_raw_progress_cb();
+        let dl_cb = self.take_raw_dl_cb();
+
+        self.set_event_cb(state.clone(), |event, state| {
+            state.borrow_mut().record_event(&event);
+            let f = state.borrow().f;
+            unsafe { (*f)(CommitProgress::Event(event)) };
+        });
+        self.set_progress_cb(
+            state.clone(),
+            |progress, pkgname, percent, howmany, current, state| {
+                let f = state.borrow().f;
+                let progress = CommitProgress::Progress {
+                    progress,
+                    pkgname,
+                    percent,
+                    howmany,
+                    current,
+                };
+                unsafe { (*f)(progress) };
+            },
+        );
+        self.set_dl_cb(state.clone(), |filename, event, state| {
+            state.borrow_mut().record_download(filename, &event);
+            let (f, rate) = {
+                let state = state.borrow();
+                (state.f, state.rate.rate())
+            };
+            let progress = CommitProgress::Download {
+                filename,
+                event,
+                rate,
+            };
+            unsafe { (*f)(progress) };
+        });
+
+        let ret = self.trans_commit();
+
+        self.set_raw_event_cb(event_cb);
+        self.set_raw_progress_cb(progress_cb);
+        self.set_raw_dl_cb(dl_cb);
+
+        ret?;
+        let mut summary = Rc::try_unwrap(state)
+            .ok()
+            .expect("callbacks were not released")
+            .into_inner()
+            .summary;
+        summary.duration = start.elapsed();
+        Ok(summary)
     }
 
-    pub fn trans_interrupt(&mut self) -> Result<()> {
+    pub fn trans_interrupt(&self) -> Result<()> {
         let ret = unsafe { alpm_trans_interrupt(self.as_ptr()) };
         self.check_ret(ret)
     }
@@ -195,24 +366,162 @@ impl Alpm {
         unsafe { AlpmList::from_ptr(list) }
     }
 
+    /// What the transaction will do to each target, as pacman lists them before committing.
+    ///
+    /// Removals come first, followed by the packages being added, each compared against the
+    /// installed version. This is only complete once the transaction has been prepared, as
+    /// preparing adds dependencies and the packages they replace.
+    pub fn trans_operations(&self) -> Vec<PackageOperation<'_>> {
+        let localdb = self.localdb();
+        let mut ops = self
+            .trans_remove()
+            .iter()
+            .map(PackageOperation::Remove)
+            .collect::<Vec<_>>();
+
+        for pkg in self.trans_add() {
+            let op = match localdb.pkg(pkg.name()) {
+                Err(_) => PackageOperation::Install(pkg),
+                Ok(old) => match pkg.version().vercmp(old.version()) {
+                    Ordering::Greater => PackageOperation::Upgrade(pkg, old),
+                    Ordering::Equal => PackageOperation::Reinstall(pkg, old),
+                    Ordering::Less => PackageOperation::Downgrade(pkg, old),
+                },
+            };
+            ops.push(op);
+        }
+
+        ops
+    }
+
     pub fn trans_release(&mut self) -> Result<()> {
-        let ret = unsafe { alpm_trans_release(self.as_ptr()) };
+        let ret = with_trans_lock(self.as_ptr(), false, || unsafe {
+            alpm_trans_release(self.as_ptr())
+        });
         self.check_ret(ret)
     }
 }
 
 impl Alpm {
     pub fn trans_init(&self, flags: TransFlag) -> Result<()> {
-        let ret = unsafe { alpm_trans_init(self.as_ptr(), flags.bits() as i32) };
+        self.check_not_in_callback()?;
+        let ret = with_trans_lock(self.as_ptr(), true, || unsafe {
+            alpm_trans_init(self.as_ptr(), flags.bits() as i32)
+        });
         self.check_ret(ret)
     }
 }
 
+/// A transaction that is released when dropped, created with [`Alpm::trans`].
+///
+/// The transaction derefs to the handle, so its databases and packages can still be borrowed
+/// and added to the transaction. Methods that would start or release another transaction are
+/// not reachable through it. Dropping it releases the transaction and with it the database
+/// lock, even on an early return or a panic. Use [`release`](Transaction::release) to see
+/// whether releasing failed.
+///
+/// ```no_run
+/// use alpm::{Alpm, SigLevel, TransFlag};
+///
+/// let mut handle = Alpm::new("/", "/var/lib/pacman").unwrap();
+/// handle.register_syncdb("core", SigLevel::USE_DEFAULT).unwrap();
+///
+/// let mut trans = handle.trans(TransFlag::NONE).unwrap();
+/// let pkg = trans.syncdbs().first().unwrap().pkg("pacman").unwrap();
+/// trans.add_pkg(pkg).unwrap();
+/// trans.prepare().unwrap();
+/// trans.commit().unwrap();
+/// trans.release().unwrap();
+/// ```
+///
+/// ```compile_fail,E0596
+/// use alpm::{Alpm, TransFlag};
+///
+/// let mut handle = Alpm::new("/", "tests/db").unwrap();
+/// let mut trans = handle.trans(TransFlag::NONE).unwrap();
+/// trans.trans_release().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Transaction<'h> {
+    handle: &'h mut Alpm,
+}
+
+impl Alpm {
+    /// Starts a transaction that is released when the returned guard is dropped.
+    pub fn trans(&mut self, flags: TransFlag) -> Result<Transaction<'_>> {
+        self.trans_init(flags)?;
+        Ok(Transaction { handle: self })
+    }
+}
+
+impl<'h> Transaction<'h> {
+    /// Adds a package to the transaction, see [`Alpm::trans_add_pkg`].
+    pub fn add_pkg<P: IntoPkgAdd>(&self, pkg: P) -> std::result::Result<(), AddError<P>> {
+        self.handle.trans_add_pkg(pkg)
+    }
+
+    /// Adds a package to be removed to the transaction, see [`Alpm::trans_remove_pkg`].
+    pub fn remove_pkg(&self, pkg: &Package) -> Result<()> {
+        self.handle.trans_remove_pkg(pkg)
+    }
+
+    /// Adds every available upgrade to the transaction, see [`Alpm::sync_sysupgrade`].
+    pub fn sysupgrade(&self, enable_downgrade: bool) -> Result<()> {
+        self.handle.sync_sysupgrade(enable_downgrade)
+    }
+
+    /// Prepares the transaction, see [`Alpm::trans_prepare`].
+    pub fn prepare(&mut self) -> std::result::Result<(), PrepareError> {
+        self.handle.trans_prepare()
+    }
+
+    /// Commits the transaction, see [`Alpm::trans_commit`].
+    pub fn commit(&mut self) -> std::result::Result<(), CommitError> {
+        self.handle.trans_commit()
+    }
+
+    /// Creates a token that can interrupt the commit from another thread, see
+    /// [`Alpm::trans_interrupter`].
+    pub fn interrupter(&self) -> TransactionInterrupter {
+        self.handle.trans_interrupter()
+    }
+
+    /// Releases the transaction, returning any error.
+    pub fn release(self) -> Result<()> {
+        let ret = self.release_inner();
+        std::mem::forget(self);
+        ret
+    }
+
+    fn release_inner(&self) -> Result<()> {
+        let handle = self.handle.as_ptr();
+        let ret = with_trans_lock(handle, false, || unsafe { alpm_trans_release(handle) });
+        self.handle.check_ret(ret)
+    }
+}
+
+impl<'h> Drop for Transaction<'h> {
+    fn drop(&mut self) {
+        let _ = self.release_inner();
+    }
+}
+
+impl<'h> Deref for Transaction<'h> {
+    type Target = Alpm;
+
+    fn deref(&self) -> &Alpm {
+        self.handle
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::{malloc_dep, malloc_str, malloc_value};
     use crate::{AnyEvent, Error, Event, LogLevel, SigLevel};
 
+    use std::ffi::c_void;
+
     fn logcb(_level: LogLevel, msg: &str, _: &mut ()) {
         print!("{}", msg);
     }
@@ -250,4 +559,176 @@ mod tests {
         // But we're only testing that the function is called correctly anyway.
         assert!(handle.trans_commit().unwrap_err().error() == Error::Retrieve);
     }
+
+    #[test]
+    fn test_trans_error_data() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+
+        let err = handle.trans_prepare().unwrap_err();
+        assert_eq!(err.error(), Error::TransNull);
+        assert!(err.data().is_none());
+        assert!(format!("{:?}", err).contains("TransNull"));
+
+        let err = handle.trans_commit().unwrap_err();
+        assert_eq!(err.error(), Error::TransNull);
+        assert!(err.into_data().is_none());
+    }
+
+    fn list<T>(items: impl IntoIterator<Item = *mut T>) -> *mut alpm_list_t {
+        items
+            .into_iter()
+            .fold(ptr::null_mut(), |list, item| unsafe {
+                alpm_list_add(list, item as *mut c_void)
+            })
+    }
+
+    #[test]
+    fn test_prepare_error_free() {
+        let archs = list([malloc_str("foo-1-1-arm"), malloc_str("bar-1-1-arm")]);
+        let err = unsafe { PrepareError::new(Error::PkgInvalidArch, archs) };
+        let Some(PrepareData::PkgInvalidArch(archs)) = err.data() else {
+            panic!("wrong data for {}", err);
+        };
+        assert_eq!(
+            archs.iter().collect::<Vec<_>>(),
+            ["foo-1-1-arm", "bar-1-1-arm"]
+        );
+
+        let missing = list([malloc_value(alpm_depmissing_t {
+            target: malloc_str("foo"),
+            depend: malloc_dep("bar>1"),
+            causingpkg: ptr::null_mut(),
+        })]);
+        let err = unsafe { PrepareError::new(Error::UnsatisfiedDeps, missing) };
+        let Some(PrepareData::UnsatisfiedDeps(missing)) = err.into_data() else {
+            panic!("wrong data");
+        };
+        assert_eq!(missing.first().unwrap().depend().to_string(), "bar>1");
+
+        let conflicts = list([malloc_value(alpm_conflict_t {
+            package1: ptr::null_mut(),
+            package2: ptr::null_mut(),
+            reason: malloc_dep("foo"),
+        })]);
+        let err = unsafe { PrepareError::new(Error::ConflictingDeps, conflicts) };
+        let Some(PrepareData::ConflictingDeps(conflicts)) = err.data() else {
+            panic!("wrong data for {}", err);
+        };
+        assert_eq!(conflicts.first().unwrap().reason().name(), "foo");
+
+        let err = unsafe { PrepareError::new(Error::TransNull, ptr::null_mut()) };
+        assert!(err.data().is_none());
+    }
+
+    #[test]
+    fn test_commit_error_free() {
+        let conflicts = list([malloc_value(alpm_fileconflict_t {
+            target: malloc_str("foo"),
+            type_: alpm_fileconflicttype_t::ALPM_FILECONFLICT_FILESYSTEM,
+            file: malloc_str("/etc/foo"),
+            ctarget: malloc_str(""),
+        })]);
+        let err = unsafe { CommitError::new(Error::FileConflicts, conflicts) };
+        let Some(CommitData::FileConflict(conflicts)) = err.data() else {
+            panic!("wrong data for {}", err);
+        };
+        assert_eq!(conflicts.first().unwrap().file(), "/etc/foo");
+
+        let invalid = list([malloc_str("/var/cache/foo.pkg.tar.zst")]);
+        let err = unsafe { CommitError::new(Error::PkgInvalidChecksum, invalid) };
+        let Some(CommitData::PkgInvalid(invalid)) = err.into_data() else {
+            panic!("wrong data");
+        };
+        assert_eq!(invalid.first(), Some("/var/cache/foo.pkg.tar.zst"));
+
+        let err = unsafe { CommitError::new(Error::TransNull, ptr::null_mut()) };
+        assert!(err.data().is_none());
+    }
+
+    #[test]
+    fn test_trans_operations() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+        handle.register_syncdb("core", SigLevel::NONE).unwrap();
+        let core = handle.syncdbs().first().unwrap();
+
+        handle.trans_init(TransFlag::NO_LOCK).unwrap();
+        handle.trans_add_pkg(core.pkg("pacman").unwrap()).unwrap();
+        handle
+            .trans_add_pkg(core.pkg("linux-lts").unwrap())
+            .unwrap();
+        handle
+            .trans_remove_pkg(handle.localdb().pkg("linux").unwrap())
+            .unwrap();
+
+        let ops = handle.trans_operations();
+        assert_eq!(ops.len(), 3);
+        assert!(matches!(ops[0], PackageOperation::Remove(p) if p.name() == "linux"));
+        match ops[1] {
+            PackageOperation::Upgrade(new, old)
+            | PackageOperation::Reinstall(new, old)
+            | PackageOperation::Downgrade(new, old) => {
+                assert_eq!(new.name(), "pacman");
+                assert_eq!(old.db().unwrap().name(), "local");
+            }
+            _ => panic!("expected pacman to be installed already"),
+        }
+        assert!(matches!(ops[2], PackageOperation::Install(p) if p.name() == "linux-lts"));
+        handle.trans_release().unwrap();
+    }
+
+    #[test]
+    fn test_transaction_guard() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+
+        let trans = handle.trans(TransFlag::NO_LOCK).unwrap();
+        assert_eq!(trans.trans_flags(), Ok(TransFlag::NO_LOCK));
+        drop(trans);
+        assert_eq!(handle.trans_flags(), Err(Error::TransNull));
+
+        let trans = handle.trans(TransFlag::NO_LOCK).unwrap();
+        trans.release().unwrap();
+        assert_eq!(handle.trans_flags(), Err(Error::TransNull));
+
+        let trans = handle.trans(TransFlag::NO_LOCK).unwrap();
+        let pkg = trans.localdb().pkg("pacman").unwrap();
+        trans.remove_pkg(pkg).unwrap();
+        assert_eq!(trans.trans_remove().len(), 1);
+        assert_eq!(trans.interrupter().interrupt(), Err(Error::TransType));
+        trans.release().unwrap();
+
+        // a transaction left by an early return is released
+        assert_eq!(early_return(&mut handle), Err(Error::TransAbort));
+        handle.trans(TransFlag::NO_LOCK).unwrap().release().unwrap();
+    }
+
+    fn early_return(handle: &mut Alpm) -> Result<()> {
+        let _trans = handle.trans(TransFlag::NO_LOCK)?;
+        Err(Error::TransAbort)
+    }
+
+    #[test]
+    fn test_trans_flags() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+        assert_eq!(handle.trans_flags(), Err(Error::TransNull));
+
+        let flags = TransFlag::NO_LOCK | TransFlag::DB_ONLY;
+        handle.trans_init(flags).unwrap();
+        assert_eq!(handle.trans_flags(), Ok(flags));
+        handle.trans_release().unwrap();
+        assert_eq!(handle.trans_flags(), Err(Error::TransNull));
+    }
+
+    #[test]
+    fn test_commit_with_progress_restores_cbs() {
+        let mut handle = Alpm::new("/", "tests/db").unwrap();
+        let data = Rc::new(());
+        handle.set_event_cb(data.clone(), |_, _| ());
+
+        let err = handle.trans_commit_with_progress(|_| ()).unwrap_err();
+        assert_eq!(err.error(), Error::TransNull);
+        assert_eq!(Rc::strong_count(&data), 2);
+
+        drop(handle);
+        assert_eq!(Rc::strong_count(&data), 1);
+    }
 }
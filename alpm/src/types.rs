@@ -1,9 +1,10 @@
+use crate::instrument::{track_free, track_new, Kind};
 use crate::utils::*;
 
 use crate::{AlpmList, Package, Pkg};
 
 use std::cell::UnsafeCell;
-use std::ffi::c_void;
+use std::ffi::{c_void, CString};
 use std::fmt;
 use std::io::{self, Read};
 use std::marker::PhantomData;
@@ -140,10 +141,30 @@ impl Group {
         unsafe { from_cstr((*self.as_ptr()).name) }
     }
 
+    /// The packages in the group. The order is not guaranteed, use [`AlpmList::sorted`] if
+    /// it matters.
     pub fn packages(&self) -> AlpmList<&Package> {
         let pkgs = unsafe { (*self.as_ptr()).packages };
         unsafe { AlpmList::from_ptr(pkgs) }
     }
+
+    pub fn len(&self) -> usize {
+        self.packages().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packages().is_empty()
+    }
+
+    pub fn pkg<S: Into<Vec<u8>>>(&self, name: S) -> Option<&Package> {
+        let name = CString::new(name).unwrap();
+        let pkg = unsafe { alpm_pkg_find(self.packages().as_ptr(), name.as_ptr()) };
+        unsafe { pkg.as_mut().map(|p| Package::from_ptr(p)) }
+    }
+
+    pub fn contains<S: Into<Vec<u8>>>(&self, name: S) -> bool {
+        self.pkg(name).is_some()
+    }
 }
 
 pub struct ChangeLog<'a> {
@@ -160,6 +181,7 @@ impl<'a> fmt::Debug for ChangeLog<'a> {
 impl<'a> Drop for ChangeLog<'a> {
     fn drop(&mut self) {
         unsafe { alpm_pkg_changelog_close(self.pkg.as_ptr(), self.as_ptr()) };
+        track_free(Kind::ChangeLog);
     }
 }
 
@@ -179,6 +201,7 @@ impl<'a> Read for ChangeLog<'a> {
 
 impl<'a> ChangeLog<'a> {
     pub(crate) unsafe fn new(pkg: &Pkg, ptr: *mut c_void) -> ChangeLog {
+        track_new(Kind::ChangeLog);
         ChangeLog {
             pkg,
             stream: NonNull::new_unchecked(ptr),
@@ -223,8 +246,10 @@ impl Backup {
         &self.inner
     }
 
-    pub fn hash(&self) -> &str {
-        unsafe { from_cstr((*self.as_ptr()).hash) }
+    /// The md5sum of the file when it was installed. Packages loaded from a file have no
+    /// hashes as `.PKGINFO` only lists the paths.
+    pub fn hash(&self) -> Option<&str> {
+        unsafe { from_cstr_optional((*self.as_ptr()).hash) }
     }
 
     pub fn name(&self) -> &str {
@@ -232,6 +257,42 @@ impl Backup {
     }
 }
 
+/// A non standard field from a package's metadata, such as `pkgtype=debug`.
+#[repr(transparent)]
+pub struct XData {
+    inner: alpm_pkg_xdata_t,
+}
+
+unsafe impl Send for XData {}
+unsafe impl Sync for XData {}
+
+impl fmt::Debug for XData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XData")
+            .field("name", &self.name())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl XData {
+    pub(crate) unsafe fn from_ptr<'a>(ptr: *mut alpm_pkg_xdata_t) -> &'a XData {
+        &*(ptr as *mut XData)
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const alpm_pkg_xdata_t {
+        &self.inner
+    }
+
+    pub fn name(&self) -> &str {
+        unsafe { from_cstr((*self.as_ptr()).name) }
+    }
+
+    pub fn value(&self) -> &str {
+        unsafe { from_cstr((*self.as_ptr()).value) }
+    }
+}
+
 pub struct AnyDownloadEvent<'a> {
     event: alpm_download_event_type_t,
     data: *mut c_void,
@@ -353,8 +414,12 @@ pub struct Signature {
     len: usize,
 }
 
+unsafe impl Send for Signature {}
+unsafe impl Sync for Signature {}
+
 impl Signature {
     pub(crate) unsafe fn new(sig: *mut c_uchar, len: usize) -> Signature {
+        track_new(Kind::Signature);
         Signature {
             sig: NonNull::new_unchecked(sig),
             len,
@@ -387,5 +452,6 @@ impl Deref for Signature {
 impl Drop for Signature {
     fn drop(&mut self) {
         unsafe { crate::free(self.as_ptr() as _) }
+        track_free(Kind::Signature);
     }
 }
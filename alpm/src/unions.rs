@@ -297,7 +297,7 @@ impl<'a> AnyEvent<'a> {
                 marker: PhantomData,
             }),
             EventType::IntegrityStart => Event::IntegrityStart,
-            EventType::IntegrityDone => Event::InterConflictsDone,
+            EventType::IntegrityDone => Event::IntegrityDone,
             EventType::LoadStart => Event::LoadStart,
             EventType::LoadDone => Event::LoadDone,
             EventType::ScriptletInfo => Event::ScriptletInfo(ScriptletInfoEvent {
@@ -320,7 +320,7 @@ impl<'a> AnyEvent<'a> {
             EventType::KeyringStart => Event::KeyringStart,
             EventType::KeyringDone => Event::KeyringDone,
             EventType::KeyDownloadStart => Event::KeyDownloadStart,
-            EventType::KeyDownloadDone => Event::KeyringDone,
+            EventType::KeyDownloadDone => Event::KeyDownloadDone,
             EventType::PacnewCreated => Event::PacnewCreated(PacnewCreatedEvent {
                 inner: unsafe { &(*event).pacnew_created },
                 marker: PhantomData,
@@ -333,7 +333,7 @@ impl<'a> AnyEvent<'a> {
                 inner: unsafe { &(*event).hook },
                 marker: PhantomData,
             }),
-            EventType::HookDone => Event::HookStart(HookEvent {
+            EventType::HookDone => Event::HookDone(HookEvent {
                 inner: unsafe { &(*event).hook },
                 marker: PhantomData,
             }),
@@ -341,7 +341,7 @@ impl<'a> AnyEvent<'a> {
                 inner: unsafe { &(*event).hook_run },
                 marker: PhantomData,
             }),
-            EventType::HookRunDone => Event::HookRunStart(HookRunEvent {
+            EventType::HookRunDone => Event::HookRunDone(HookRunEvent {
                 inner: unsafe { &(*event).hook_run },
                 marker: PhantomData,
             }),
@@ -478,10 +478,15 @@ impl<'a> HookRunEvent<'a> {
 }
 
 impl<'a> PkgRetrieveEvent<'a> {
+    /// The number of packages to download.
     pub fn num(&self) -> usize {
         unsafe { (*self.inner).num }
     }
 
+    /// The combined size in bytes of the packages to download.
+    ///
+    /// This replaces the total download callback of older versions of libalpm and can be
+    /// used to draw a progress bar across all of the downloads.
     #[allow(clippy::useless_conversion)]
     pub fn total_size(&self) -> i64 {
         unsafe { (*self.inner).total_size.into() }
@@ -839,3 +844,87 @@ impl<'a> ImportKeyQuestion<'a> {
         unsafe { from_cstr((*self.inner).fingerprint) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event: alpm_event_t) -> String {
+        format!("{:?}", unsafe { AnyEvent::new(&event) }.event())
+    }
+
+    fn any(type_: alpm_event_type_t) -> alpm_event_t {
+        alpm_event_t {
+            any: alpm_event_any_t { type_ },
+        }
+    }
+
+    #[test]
+    fn test_event_done() {
+        assert_eq!(event(any(ALPM_EVENT_INTEGRITY_DONE)), "IntegrityDone");
+        assert_eq!(event(any(ALPM_EVENT_KEY_DOWNLOAD_DONE)), "KeyDownloadDone");
+
+        let hook = alpm_event_t {
+            hook: alpm_event_hook_t {
+                type_: ALPM_EVENT_HOOK_DONE,
+                when: ALPM_HOOK_POST_TRANSACTION,
+            },
+        };
+        assert_eq!(event(hook), "HookDone(HookEvent { when: PostTransaction })");
+
+        let hook_run = alpm_event_t {
+            hook_run: alpm_event_hook_run_t {
+                type_: ALPM_EVENT_HOOK_RUN_DONE,
+                name: c"test".as_ptr(),
+                desc: std::ptr::null(),
+                position: 1,
+                total: 2,
+            },
+        };
+        assert_eq!(
+            event(hook_run),
+            "HookRunDone(HookRunEvent { name: \"test\", desc: None, position: 1, total: 2 })"
+        );
+    }
+
+    #[test]
+    fn test_question_answers() {
+        let mut question = alpm_question_t {
+            select_provider: alpm_question_select_provider_t {
+                type_: ALPM_QUESTION_SELECT_PROVIDER,
+                use_index: 0,
+                providers: std::ptr::null_mut(),
+                depend: std::ptr::null_mut(),
+            },
+        };
+        let any = unsafe { AnyQuestion::new(&mut question) };
+        assert_eq!(any.question_type(), QuestionType::SelectProvider);
+        match any.question() {
+            Question::SelectProvider(mut q) => {
+                assert!(q.providers().is_empty());
+                q.set_index(2);
+                assert_eq!(q.index(), 2);
+            }
+            _ => panic!("expected select provider question"),
+        }
+        assert_eq!(unsafe { question.select_provider.use_index }, 2);
+
+        let mut question = alpm_question_t {
+            conflict: alpm_question_conflict_t {
+                type_: ALPM_QUESTION_CONFLICT_PKG,
+                remove: 0,
+                conflict: std::ptr::null_mut(),
+            },
+        };
+        let any = unsafe { AnyQuestion::new(&mut question) };
+        match any.question() {
+            Question::Conflict(mut q) => q.set_remove(true),
+            _ => panic!("expected conflict question"),
+        }
+        assert_eq!(unsafe { question.conflict.remove }, 1);
+
+        let mut any = unsafe { AnyQuestion::new(&mut question) };
+        any.set_answer(false);
+        assert_eq!(unsafe { question.any.answer }, 0);
+    }
+}
@@ -7,5 +7,45 @@ pub unsafe fn from_cstr<'a>(s: *const c_char) -> &'a str {
 }
 
 pub unsafe fn from_cstr_optional<'a>(s: *const c_char) -> Option<&'a str> {
-    s.as_ref().map(|s| CStr::from_ptr(s).to_str().unwrap())
+    // not s.as_ref(), a reference to the first byte can not be used to read the rest
+    if s.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(s).to_str().unwrap())
+    }
+}
+
+// Helpers for building the values libalpm hands back, so the code that frees them can be
+// tested without a handle, including under miri with the mock feature.
+
+#[cfg(test)]
+extern "C" {
+    fn malloc(size: usize) -> *mut std::ffi::c_void;
+}
+
+#[cfg(test)]
+pub(crate) fn malloc_value<T>(value: T) -> *mut T {
+    unsafe {
+        let ptr = malloc(std::mem::size_of::<T>()) as *mut T;
+        assert!(!ptr.is_null());
+        ptr.write(value);
+        ptr
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn malloc_str(s: &str) -> *mut c_char {
+    unsafe {
+        let ptr = malloc(s.len() + 1) as *mut c_char;
+        assert!(!ptr.is_null());
+        std::ptr::copy_nonoverlapping(s.as_ptr() as *const c_char, ptr, s.len());
+        *ptr.add(s.len()) = 0;
+        ptr
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn malloc_dep(s: &str) -> *mut alpm_sys::alpm_depend_t {
+    let s = std::ffi::CString::new(s).unwrap();
+    unsafe { alpm_sys::alpm_dep_from_string(s.as_ptr()) }
 }
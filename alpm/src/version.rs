@@ -30,6 +30,16 @@ impl Ver {
         unsafe { alpm_pkg_vercmp(self.0.as_ptr(), other.as_ref().0.as_ptr()).cmp(&0) }
     }
 
+    /// The epoch of the version, the number before the `:`, if it has one.
+    pub fn epoch(&self) -> Option<&str> {
+        split_epoch(self).0
+    }
+
+    /// The version without its epoch.
+    pub fn without_epoch(&self) -> &str {
+        split_epoch(self).1
+    }
+
     pub(crate) unsafe fn from_ptr<'a>(s: *const c_char) -> &'a Ver {
         Ver::new(CStr::from_ptr(s))
     }
@@ -201,11 +211,62 @@ impl PartialEq<Version> for String {
     }
 }
 
+fn split_epoch(ver: &str) -> (Option<&str>, &str) {
+    match ver.split_once(':') {
+        Some((epoch, rest)) if !epoch.is_empty() && epoch.bytes().all(|b| b.is_ascii_digit()) => {
+            (Some(epoch), rest)
+        }
+        _ => (None, ver),
+    }
+}
+
+/// Formats a change of version as `old -> new`, the way upgrades are listed.
+///
+/// Epochs are shown by default. With [`hide_epoch`](VersionChange::hide_epoch) they are
+/// left out when both versions have the same epoch, but still shown when the epoch changes
+/// as the change would be misleading otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VersionChange<'a> {
+    old: &'a str,
+    new: &'a str,
+    hide_epoch: bool,
+}
+
+impl<'a> VersionChange<'a> {
+    pub fn new(old: &'a str, new: &'a str) -> Self {
+        VersionChange {
+            old,
+            new,
+            hide_epoch: false,
+        }
+    }
+
+    /// Leaves out the epoch if it does not change.
+    pub fn hide_epoch(mut self) -> Self {
+        self.hide_epoch = true;
+        self
+    }
+}
+
+impl fmt::Display for VersionChange<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (old_epoch, old) = split_epoch(self.old);
+        let (new_epoch, new) = split_epoch(self.new);
+
+        if self.hide_epoch && old_epoch == new_epoch {
+            write!(f, "{} -> {}", old, new)
+        } else {
+            write!(f, "{} -> {}", self.old, self.new)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use crate::Depend;
+    use proptest::prelude::*;
 
     #[test]
     fn test_version() {
@@ -234,4 +295,112 @@ mod tests {
         assert!(dep2.version().unwrap() >= Version::new("34"));
         assert!(Version::new("1.9.3-2") < Version::new("1.10.2-1"));
     }
+
+    #[test]
+    fn test_epoch() {
+        let ver = Version::new("1:2.0-1");
+        assert_eq!(ver.epoch(), Some("1"));
+        assert_eq!(ver.without_epoch(), "2.0-1");
+
+        let ver = Version::new("2.0-1");
+        assert_eq!(ver.epoch(), None);
+        assert_eq!(ver.without_epoch(), "2.0-1");
+
+        let ver = Version::new("a:2.0");
+        assert_eq!(ver.epoch(), None);
+        assert_eq!(ver.without_epoch(), "a:2.0");
+    }
+
+    #[test]
+    fn test_version_change() {
+        let change = VersionChange::new("1.2.3-1", "1:1.0-1");
+        assert_eq!(change.to_string(), "1.2.3-1 -> 1:1.0-1");
+        assert_eq!(change.hide_epoch().to_string(), "1.2.3-1 -> 1:1.0-1");
+
+        let change = VersionChange::new("2:1.0-1", "2:1.1-1");
+        assert_eq!(change.to_string(), "2:1.0-1 -> 2:1.1-1");
+        assert_eq!(change.hide_epoch().to_string(), "1.0-1 -> 1.1-1");
+    }
+
+    #[test]
+    fn test_vercmp_properties() {
+        let versions = [
+            "",
+            "0",
+            "1",
+            "1.0",
+            "1.0.0",
+            "1.0a",
+            "1.0alpha",
+            "1.0.1",
+            "1.1",
+            "1.10",
+            "1_0",
+            "1+1",
+            "1-1",
+            "1-2",
+            "1.0-1.1",
+            "1:0",
+            "2:1.0-1",
+            "0:1",
+            "a",
+            "1a1",
+            "1..0",
+            "1.0rc1",
+            "1.0.r3.gabcdef",
+            "20240101",
+            "~1",
+            "1:",
+        ];
+
+        for a in versions {
+            assert_eq!(vercmp(a, a), Ordering::Equal, "{:?}", a);
+            for b in versions {
+                let ab = vercmp(a, b);
+                assert_eq!(ab, vercmp(b, a).reverse(), "{:?} {:?}", a, b);
+                assert_eq!(
+                    Version::new(a).partial_cmp(&Version::new(b)),
+                    Some(ab),
+                    "{:?} {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    // epochs, dotted alphanumeric segments and pkgrels, mixing the cases vercmp
+    // treats specially (letters sort before numbers, empty and missing segments)
+    fn version() -> impl Strategy<Value = String> {
+        "([0-9]{1,2}:)?[0-9a-z]{0,3}([._+][0-9a-z]{0,3}){0,3}(-[0-9]{1,2}(\\.[0-9])?)?"
+    }
+
+    proptest! {
+        #[test]
+        fn prop_vercmp_antisymmetric(a in version(), b in version()) {
+            prop_assert_eq!(vercmp(a.as_str(), a.as_str()), Ordering::Equal);
+            prop_assert_eq!(
+                vercmp(a.as_str(), b.as_str()),
+                vercmp(b.as_str(), a.as_str()).reverse()
+            );
+        }
+
+        // a cycle a < b < c < a leaves some out of order pair in any arrangement,
+        // so checking every pair after sorting catches intransitive comparisons
+        #[test]
+        fn prop_vercmp_transitive(mut versions in prop::collection::vec(version(), 2..12)) {
+            versions.sort_by(|a, b| vercmp(a.as_str(), b.as_str()));
+            for (i, a) in versions.iter().enumerate() {
+                for b in &versions[i + 1..] {
+                    prop_assert_ne!(
+                        vercmp(a.as_str(), b.as_str()),
+                        Ordering::Greater,
+                        "{:?} {:?}",
+                        a,
+                        b
+                    );
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,37 @@
+//! Checks the live object counts. This is a separate test binary with a single test so that
+//! no other test creates objects while the counts are being compared.
+
+#![cfg(feature = "instrument")]
+
+use alpm::{live_objects, Alpm, AlpmListMut, Depend, SigLevel};
+
+#[test]
+fn test_live_objects() {
+    let before = live_objects();
+
+    let handle = Alpm::new("/", "tests/db").unwrap();
+    assert_eq!(live_objects().handles, before.handles + 1);
+
+    let dep = Depend::new("foo>=1");
+    let list = vec![dep.clone()]
+        .into_iter()
+        .collect::<AlpmListMut<Depend>>();
+    let now = live_objects();
+    assert_eq!(now.depends, before.depends + 2);
+    assert_eq!(now.lists, before.lists + 1);
+    drop(list);
+    drop(dep);
+
+    let pkg = handle
+        .pkg_load(
+            "tests/pacman-5.1.3-1-x86_64.pkg.tar.xz",
+            true,
+            SigLevel::NONE,
+        )
+        .unwrap();
+    assert_eq!(live_objects().loaded_packages, before.loaded_packages + 1);
+    drop(pkg);
+
+    drop(handle);
+    assert_eq!(live_objects(), before);
+}
@@ -0,0 +1,184 @@
+//! Compares the results of the bindings against the system pacman on the test databases.
+//!
+//! These tests are skipped when pacman is not installed.
+
+use alpm::{vercmp, Alpm, AlpmList, Dep, Pkg, SigLevel};
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::process::Command;
+
+const DBPATH: &str = "tests/db";
+
+fn pacman(args: &[&str]) -> Option<String> {
+    let conf = tempfile::NamedTempFile::new().unwrap();
+    fs::write(&conf, "[options]\nSigLevel = Never\n\n[core]\n").unwrap();
+
+    let output = Command::new("pacman")
+        .env("LC_ALL", "C")
+        .arg("--config")
+        .arg(conf.path())
+        .args(["--dbpath", DBPATH, "--root", "/", "--color", "never"])
+        .args(args)
+        .output();
+
+    match output {
+        Ok(output) => {
+            assert!(output.status.success(), "pacman {:?} failed", args);
+            Some(String::from_utf8(output.stdout).unwrap())
+        }
+        Err(_) => {
+            eprintln!("pacman not found, skipping");
+            None
+        }
+    }
+}
+
+// Parses the key : value blocks printed by -Si and -Qi.
+fn parse_info(output: &str) -> Vec<HashMap<String, String>> {
+    let mut ret = Vec::new();
+
+    for block in output.split("\n\n").filter(|b| !b.trim().is_empty()) {
+        let mut info = HashMap::new();
+        let mut last = String::new();
+
+        for line in block.lines() {
+            if line.starts_with(' ') {
+                let value: &mut String = info.get_mut(&last).unwrap();
+                value.push('\n');
+                value.push_str(line.trim());
+            } else if let Some((key, value)) = line.split_once(" : ") {
+                last = key.trim().to_string();
+                info.insert(last.clone(), value.trim().to_string());
+            }
+        }
+
+        ret.push(info);
+    }
+
+    ret
+}
+
+fn join<'a>(list: impl IntoIterator<Item = &'a str>, sep: &str) -> String {
+    let list = list.into_iter().collect::<Vec<_>>();
+    if list.is_empty() {
+        "None".to_string()
+    } else {
+        list.join(sep)
+    }
+}
+
+fn join_deps(list: AlpmList<&Dep>, sep: &str) -> String {
+    let list = list.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+    join(list.iter().map(|s| s.as_str()), sep)
+}
+
+fn or_none(s: Option<&str>) -> &str {
+    s.filter(|s| !s.is_empty()).unwrap_or("None")
+}
+
+fn check_common(pkg: &Pkg, info: &HashMap<String, String>) {
+    let name = pkg.name();
+    let check = |key: &str, value: &str| assert_eq!(info[key], value, "{} {}", name, key);
+
+    check("Name", name);
+    check("Version", pkg.version());
+    check("Description", or_none(pkg.desc()));
+    check("Architecture", or_none(pkg.arch()));
+    check("URL", or_none(pkg.url()));
+    check("Licenses", &join(pkg.licenses(), "  "));
+    check("Groups", &join(pkg.groups(), "  "));
+    check("Provides", &join_deps(pkg.provides(), "  "));
+    check("Depends On", &join_deps(pkg.depends(), "  "));
+    check("Conflicts With", &join_deps(pkg.conflicts(), "  "));
+    check("Replaces", &join_deps(pkg.replaces(), "  "));
+    check("Packager", or_none(pkg.packager()));
+
+    // -Qi marks optional deps that are installed
+    let optdepends = info["Optional Deps"].replace(" [installed]", "");
+    assert_eq!(optdepends, join_deps(pkg.optdepends(), "\n"), "{}", name);
+}
+
+#[test]
+fn test_sync_info() {
+    let handle = Alpm::new("/", DBPATH).unwrap();
+    let db = handle.register_syncdb("core", SigLevel::NONE).unwrap();
+
+    let Some(output) = pacman(&["-Si"]) else {
+        return;
+    };
+    let infos = parse_info(&output);
+    assert_eq!(infos.len(), db.pkgs().len());
+
+    for info in &infos {
+        assert_eq!(info["Repository"], "core");
+        let pkg = db.pkg(info["Name"].as_str()).unwrap();
+        check_common(pkg, info);
+    }
+}
+
+#[test]
+fn test_local_info() {
+    let handle = Alpm::new("/", DBPATH).unwrap();
+    let db = handle.localdb();
+
+    let Some(output) = pacman(&["-Qi"]) else {
+        return;
+    };
+    let infos = parse_info(&output);
+    assert_eq!(infos.len(), db.pkgs().len());
+
+    for info in &infos {
+        let pkg = db.pkg(info["Name"].as_str()).unwrap();
+        check_common(pkg, info);
+
+        let mut expected = info["Required By"]
+            .split_whitespace()
+            .filter(|s| *s != "None")
+            .collect::<Vec<_>>();
+        let required_by = pkg.required_by();
+        let mut required_by = required_by.iter().collect::<Vec<_>>();
+        expected.sort_unstable();
+        required_by.sort_unstable();
+        assert_eq!(expected, required_by, "{}", pkg.name());
+    }
+}
+
+#[test]
+fn test_vercmp() {
+    let versions = [
+        "0",
+        "1",
+        "1.0",
+        "1.0a",
+        "1.0.1",
+        "1.10",
+        "1-1",
+        "1-2",
+        "1:0",
+        "2:1.0-1",
+        "1.0rc1",
+        "1.0.r3.gabcdef",
+        "20240101",
+    ];
+
+    for a in versions {
+        for b in versions {
+            let output = match Command::new("vercmp").args([a, b]).output() {
+                Ok(output) => output,
+                Err(_) => {
+                    eprintln!("vercmp not found, skipping");
+                    return;
+                }
+            };
+            let expected = match String::from_utf8(output.stdout).unwrap().trim() {
+                "-1" => Ordering::Less,
+                "0" => Ordering::Equal,
+                "1" => Ordering::Greater,
+                s => panic!("unexpected vercmp output {:?}", s),
+            };
+            assert_eq!(vercmp(a, b), expected, "{} {}", a, b);
+        }
+    }
+}
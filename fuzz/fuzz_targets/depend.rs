@@ -0,0 +1,18 @@
+#![no_main]
+
+use alpm::Depend;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|s: &str| {
+    if s.contains('\0') {
+        return;
+    }
+
+    let dep = Depend::new(s);
+    let again = Depend::new(dep.to_string());
+
+    assert_eq!(dep.name(), again.name());
+    assert_eq!(dep.depmod(), again.depmod());
+    assert_eq!(dep.version(), again.version());
+    assert_eq!(dep.desc(), again.desc());
+});
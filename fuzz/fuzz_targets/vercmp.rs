@@ -0,0 +1,17 @@
+#![no_main]
+
+use alpm::{vercmp, Version};
+use libfuzzer_sys::fuzz_target;
+use std::cmp::Ordering;
+
+fuzz_target!(|input: (&str, &str)| {
+    let (a, b) = input;
+    if a.contains('\0') || b.contains('\0') {
+        return;
+    }
+
+    let ab = vercmp(a, b);
+    assert_eq!(vercmp(a, a), Ordering::Equal);
+    assert_eq!(ab, vercmp(b, a).reverse());
+    assert_eq!(Version::new(a).partial_cmp(&Version::new(b)), Some(ab));
+});